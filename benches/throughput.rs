@@ -0,0 +1,35 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use playground_metrics::{rewrite, Converter, DAVINCI};
+
+const PARAGRAPH: &str = "So I finally weighed the parcel: 2.5 kg including the box, which is \
+                         about 40 cm long and cost me $35 to ship 1200 km. The battery inside \
+                         is a 5000 mAh pack rated at 18.5 Wh, and the whole thing was 250€. \
+                         Nothing else in this sentence should match, not even 42 or the year \
+                         2023 or a stray 7.\n";
+
+/// Builds a message of roughly `len` bytes by repeating a paragraph that mixes
+/// plain text with amounts, like a long pasted message.
+fn message(len: usize) -> String {
+    PARAGRAPH.repeat(len / PARAGRAPH.len() + 1)
+}
+
+fn convert(c: &mut Criterion) {
+    let converter = Converter::default();
+    let mut group = c.benchmark_group("convert_text");
+
+    for len in [1 << 10, 1 << 14, 1 << 18] {
+        let text = message(len);
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(len), &text, |b, text| {
+            b.iter(|| {
+                let conversions = converter.convert_text(black_box(text), &DAVINCI);
+                rewrite(text, &conversions)
+            });
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, convert);
+criterion_main!(benches);
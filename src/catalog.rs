@@ -0,0 +1,191 @@
+use std::{collections::HashMap, fmt, fs, io, path::Path};
+
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::Unit;
+
+/// The catalog compiled into the binary, used when no catalog file is given.
+const BUILTIN: &str = include_str!("units.toml");
+
+/// The set of units a [`Converter`](crate::Converter) recognises.
+#[derive(Debug)]
+pub struct Catalog {
+    units: Vec<Unit>,
+}
+
+/// The layout of a catalog file: a list of `[[unit]]` tables.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CatalogFile {
+    unit: Vec<Spanned<Unit>>,
+}
+
+/// Why a catalog could not be loaded.
+#[derive(Debug)]
+pub enum CatalogError {
+    /// The catalog file could not be read.
+    Io(io::Error),
+    /// The catalog is not valid TOML or does not have the expected shape.
+    Syntax(toml::de::Error),
+    /// The catalog parsed, but some of its entries make no sense.
+    Invalid(Vec<InvalidEntry>),
+}
+
+/// A problem with a single `[[unit]]` entry of a catalog.
+#[derive(Debug)]
+pub struct InvalidEntry {
+    /// The 1-based line the entry starts on.
+    pub line: usize,
+    pub message: String,
+}
+
+impl Catalog {
+    /// Parses and validates a catalog in the format of the built-in one.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CatalogError::Syntax`] if `source` is not a well-formed
+    /// catalog, or [`CatalogError::Invalid`] listing every entry that fails
+    /// validation.
+    pub fn parse(source: &str) -> Result<Self, CatalogError> {
+        let file: CatalogFile = toml::from_str(source).map_err(CatalogError::Syntax)?;
+
+        let mut errors = Vec::new();
+        // Lowercased alias -> the entries it was already seen in, with the
+        // spelling it has to be written in if it is case-sensitive
+        let mut seen: HashMap<String, Vec<(usize, Option<&str>)>> = HashMap::new();
+
+        for (idx, entry) in file.unit.iter().enumerate() {
+            let line = line_of(source, entry.span().start);
+            let mut invalid = |message: String| errors.push(InvalidEntry { line, message });
+            let unit = entry.get_ref();
+
+            if unit.name.trim().is_empty() || unit.plural.trim().is_empty() {
+                invalid("`name` and `plural` must not be empty".to_owned());
+            }
+            if !unit.factor.is_finite() || unit.factor <= 0.0 {
+                invalid(format!(
+                    "`factor` must be a positive number, got {}",
+                    unit.factor
+                ));
+            }
+            if !unit.offset.is_finite() {
+                invalid(format!(
+                    "`offset` must be a finite number, got {}",
+                    unit.offset
+                ));
+            }
+            if unit.aliases.is_empty() {
+                invalid("`aliases` must not be empty".to_owned());
+            }
+
+            for alias in &unit.compound_aliases {
+                if alias.is_empty() || alias.trim() != alias {
+                    invalid(format!(
+                        "compound alias {alias:?} must not be empty or start or end with whitespace"
+                    ));
+                }
+            }
+
+            let aliases = unit
+                .aliases
+                .iter()
+                .chain(&unit.weak_aliases)
+                .map(|alias| (alias, None))
+                .chain(
+                    unit.case_sensitive_aliases
+                        .iter()
+                        .map(|alias| (alias, Some(alias.as_str()))),
+                );
+            for (alias, spelling) in aliases {
+                if alias.is_empty() || alias.trim() != alias {
+                    invalid(format!(
+                        "alias {alias:?} must not be empty or start or end with whitespace"
+                    ));
+                    continue;
+                }
+
+                // Units of different metrics may share an alias, which is then
+                // resolved from the words around each match. Units of the same
+                // metric may only share it in different cases, like "mW" and
+                // "MW".
+                let others = seen.entry(alias.to_lowercase()).or_default();
+                if let Some(&(other, _)) = others.iter().find(|&&(other, other_spelling)| {
+                    let other = file.unit[other].get_ref();
+                    other.metric == unit.metric
+                        && other.position.overlaps(unit.position)
+                        && (spelling.is_none()
+                            || other_spelling.is_none()
+                            || spelling == other_spelling)
+                }) {
+                    invalid(format!(
+                        "alias {alias:?} is already used by the entry on line {}",
+                        line_of(source, file.unit[other].span().start)
+                    ));
+                }
+                others.push((idx, spelling));
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(CatalogError::Invalid(errors));
+        }
+
+        Ok(Self {
+            units: file.unit.into_iter().map(Spanned::into_inner).collect(),
+        })
+    }
+
+    /// Reads and parses the catalog file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CatalogError::Io`] if the file cannot be read, otherwise the
+    /// same errors as [`Catalog::parse`].
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, CatalogError> {
+        let source = fs::read_to_string(path).map_err(CatalogError::Io)?;
+        Self::parse(&source)
+    }
+
+    #[must_use]
+    pub fn units(&self) -> &[Unit] {
+        &self.units
+    }
+}
+
+impl Default for Catalog {
+    fn default() -> Self {
+        Self::parse(BUILTIN).expect("the built-in catalog is valid")
+    }
+}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatalogError::Io(err) => write!(f, "could not read unit catalog: {err}"),
+            CatalogError::Syntax(err) => write!(f, "malformed unit catalog: {err}"),
+            CatalogError::Invalid(entries) => {
+                write!(f, "invalid unit catalog:")?;
+                for entry in entries {
+                    write!(f, "\n  line {}: {}", entry.line, entry.message)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CatalogError::Io(err) => Some(err),
+            CatalogError::Syntax(err) => Some(err),
+            CatalogError::Invalid(_) => None,
+        }
+    }
+}
+
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset].matches('\n').count() + 1
+}
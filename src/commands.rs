@@ -0,0 +1,609 @@
+use std::{fmt::Write, ops::Range, sync::Arc};
+
+use playground_metrics::{
+    find_reference, format_significant, Conversion, ConvertOptions, Converter, Locale, Metric,
+    OutputStyle, Precision, ReferenceObject, ReverseError, Rewritten, REFERENCE_OBJECTS,
+};
+use teloxide::{
+    prelude::*,
+    types::{MessageEntity, MessageEntityKind},
+    utils::command::BotCommands,
+};
+
+use crate::{
+    reload::{self, SharedConverter},
+    settings::{ChatSettings, EntityKind, Mode, SettingsStore},
+};
+
+const ABOUT: &str = "Playground metrics expresses the amounts in your messages in davincis \
+                     (Redmi K20s) or other familiar objects. Mention a weight, length, area, \
+                     volume, battery charge, energy, power, price, temperature, duration, \
+                     speed, amount of data or data rate and it replies with the converted \
+                     message.
+
+Source: https://github.com/JeelPatel231/playground-metrics";
+
+const SETTINGS_USAGE: &str = "Usage:
+/settings - show the settings of this chat
+/settings reference <name> - express amounts in another object
+/settings style <replace|annotate|list> - change how conversions are shown
+/settings enable <metric> - convert amounts of a metric
+/settings disable <metric> - stop converting amounts of a metric
+/settings mode <auto|on-demand> - convert every message or only on request
+/settings prefer <metric|none> - favour a metric for ambiguous units like pounds
+/settings skip <kind> - never convert code, urls, mentions and such
+/settings unskip <kind> - convert amounts in that kind of text again
+/settings locale <auto|point|comma> - read 1,5 as one and a half or not
+/settings reset - go back to the defaults";
+
+#[derive(BotCommands, Clone)]
+#[command(
+    rename_rule = "lowercase",
+    description = "These commands are supported:"
+)]
+pub enum Command {
+    #[command(description = "show this text.")]
+    Help,
+    #[command(description = "list the units that are recognised.")]
+    Units,
+    #[command(
+        description = "convert the given text, or the message replied to. Start with \"as list\" \
+                       or another style to show it differently."
+    )]
+    Convert(String),
+    #[command(description = "show how the given text, or the message replied to, is converted.")]
+    Explain(String),
+    #[command(description = "view or change the settings of this chat.")]
+    Settings(String),
+    #[command(description = "tell what this bot is about.")]
+    About,
+}
+
+pub async fn answer(
+    bot: Bot,
+    converter: SharedConverter,
+    settings: Arc<SettingsStore>,
+    msg: Message,
+    cmd: Command,
+) -> ResponseResult<()> {
+    let converter = reload::current(&converter);
+    let mut chat_settings = settings.get(msg.chat.id);
+
+    let mut entities = Vec::new();
+    let answer = match cmd {
+        Command::Help => Command::descriptions().to_string(),
+        Command::Units => list_units(&converter),
+        Command::Convert(text) => {
+            match command_text(&msg, strip_style(&text, &mut chat_settings)) {
+                Some((source, text)) => {
+                    let formatting = text_entities(source, text);
+                    let options =
+                        chat_settings.options(skipped_ranges(&formatting, &chat_settings));
+                    match convert_reply(
+                        &converter,
+                        &chat_settings,
+                        &options,
+                        text,
+                        &formatting,
+                        true,
+                    ) {
+                        Some((reply, reply_entities)) => {
+                            entities = reply_entities;
+                            reply
+                        }
+                        None => "I could not find any amounts to convert.".to_owned(),
+                    }
+                }
+                None => {
+                    "Send /convert followed by some text, or reply to a message with it.".to_owned()
+                }
+            }
+        }
+        Command::Explain(text) => match command_text(&msg, &text) {
+            Some((source, text)) => {
+                let formatting = text_entities(source, text);
+                let options = chat_settings.options(skipped_ranges(&formatting, &chat_settings));
+                explain(
+                    &conversions(&converter, &chat_settings, &options, text),
+                    text,
+                )
+            }
+            None => "Reply to a message with /explain to see how it was converted.".to_owned(),
+        },
+        Command::Settings(args) => change_settings(&settings, msg.chat.id, &args).await,
+        Command::About => ABOUT.to_owned(),
+    };
+
+    reply(&bot, &msg, answer, entities).await;
+
+    Ok(())
+}
+
+/// Converts `text` the way the chat is set up to, leaving out metrics the chat
+/// disabled. Matches that fail to convert are logged and skipped.
+pub fn conversions<'a>(
+    converter: &'a Converter,
+    settings: &ChatSettings,
+    options: &ConvertOptions,
+    text: &str,
+) -> Vec<Conversion<'a>> {
+    converter
+        .try_convert_text(text, settings.reference(), options)
+        .into_iter()
+        .filter_map(|conversion| {
+            conversion
+                .inspect_err(|err| eprintln!("Skipping a match: {err}"))
+                .ok()
+        })
+        .filter(|conversion| settings.converts(conversion.metric()))
+        .collect()
+}
+
+/// Replies to `msg` with `text`, formatted with `entities`. Failing to send is
+/// logged rather than passed on, so one undeliverable reply never affects
+/// other messages.
+pub async fn reply(bot: &Bot, msg: &Message, text: String, entities: Vec<MessageEntity>) {
+    if let Err(err) = bot
+        .send_message(msg.chat.id, text)
+        .entities(entities)
+        .reply_to_message_id(msg.id)
+        .await
+    {
+        eprintln!("Could not reply in chat {}: {err}", msg.chat.id);
+    }
+}
+
+/// Builds the reply to `text`: the text with its amounts converted, followed
+/// by the answers to queries like "12 davincis in kg". Returns `None` if there
+/// is nothing to reply, or else the reply and the entities formatting it like
+/// the `formatting` of `text`.
+///
+/// Queries naming an unknown unit are only reported if the conversion was
+/// `requested` with a command, as in passing "3 bananas in total" is not a
+/// question.
+pub fn convert_reply(
+    converter: &Converter,
+    settings: &ChatSettings,
+    options: &ConvertOptions,
+    text: &str,
+    formatting: &[(MessageEntityKind, Range<usize>)],
+    requested: bool,
+) -> Option<(String, Vec<MessageEntity>)> {
+    let conversions = conversions(converter, settings, options, text);
+    let answers: Vec<String> = converter
+        .convert_reverse(text, options)
+        .into_iter()
+        .filter_map(|answer| match answer {
+            Ok(answer) => Some(answer.to_string()),
+            Err(ReverseError::UnknownUnit(_)) if !requested => None,
+            Err(err) => Some(err.to_string()),
+        })
+        .collect();
+
+    let (mut reply, entities) = if conversions.is_empty() {
+        (String::new(), Vec::new())
+    } else {
+        let rewritten = Rewritten::new(text, &conversions, settings.style);
+        let entities = formatting
+            .iter()
+            .filter(|(kind, _)| is_formatting(kind))
+            .filter_map(|(kind, range)| {
+                entity(&rewritten.text, kind.clone(), &rewritten.map(range)?)
+            })
+            .collect();
+        (rewritten.text, entities)
+    };
+
+    if !answers.is_empty() {
+        if !reply.is_empty() {
+            reply.push_str("\n\n");
+        }
+        reply.push_str(&answers.join("\n"));
+    }
+
+    (!reply.trim().is_empty()).then_some((reply, entities))
+}
+
+/// The entities of `msg` that fall within `text`, with their kind and byte
+/// range in `text`. `text` is the text of `msg`, or the end of it for the
+/// arguments of a command.
+pub fn text_entities(msg: &Message, text: &str) -> Vec<(MessageEntityKind, Range<usize>)> {
+    let (Some(full), Some(entities)) = (msg.text(), msg.parse_entities()) else {
+        return Vec::new();
+    };
+    let Some(offset) = full
+        .len()
+        .checked_sub(text.len())
+        .filter(|_| full.ends_with(text))
+    else {
+        return Vec::new();
+    };
+
+    entities
+        .iter()
+        .filter(|entity| entity.end() > offset)
+        .map(|entity| {
+            let range = entity.start().saturating_sub(offset)..entity.end() - offset;
+            (entity.kind().clone(), range)
+        })
+        .collect()
+}
+
+/// Byte ranges of the text the chat does not want converted, like code blocks
+/// and links, out of the text's `entities`.
+pub fn skipped_ranges(
+    entities: &[(MessageEntityKind, Range<usize>)],
+    settings: &ChatSettings,
+) -> Vec<Range<usize>> {
+    entities
+        .iter()
+        .filter(|(kind, _)| EntityKind::of(kind).is_some_and(|kind| settings.skips(kind)))
+        .map(|(_, range)| range.clone())
+        .collect()
+}
+
+/// Whether an entity of `kind` is formatting the sender chose, rather than
+/// something like a url or mention Telegram finds in the reply by itself.
+fn is_formatting(kind: &MessageEntityKind) -> bool {
+    matches!(
+        kind,
+        MessageEntityKind::Bold
+            | MessageEntityKind::Italic
+            | MessageEntityKind::Underline
+            | MessageEntityKind::Strikethrough
+            | MessageEntityKind::Spoiler
+            | MessageEntityKind::Code
+            | MessageEntityKind::Pre { .. }
+            | MessageEntityKind::TextLink { .. }
+            | MessageEntityKind::TextMention { .. }
+            | MessageEntityKind::CustomEmoji { .. }
+    )
+}
+
+/// An entity of `kind` covering the byte `range` of `text`, with its offset
+/// and length counted in UTF-16 code units as Telegram expects. Returns
+/// `None` for an empty range.
+fn entity(text: &str, kind: MessageEntityKind, range: &Range<usize>) -> Option<MessageEntity> {
+    let utf16_len = |text: &str| text.encode_utf16().count();
+
+    (!range.is_empty()).then(|| {
+        MessageEntity::new(
+            kind,
+            utf16_len(&text[..range.start]),
+            utf16_len(&text[range.clone()]),
+        )
+    })
+}
+
+/// Strips a leading "as <style>" from the arguments of `/convert`, showing the
+/// conversions in that style instead of the chat's.
+fn strip_style<'a>(args: &'a str, settings: &mut ChatSettings) -> &'a str {
+    let Some(rest) = args.trim_start().strip_prefix("as ") else {
+        return args;
+    };
+    let rest = rest.trim_start();
+    let (style, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+
+    match style.parse() {
+        Ok(style) => {
+            settings.style = style;
+            rest
+        }
+        Err(_) => args,
+    }
+}
+
+/// The text a command should work on: its arguments, or else the text of the
+/// message it replies to. Returned along with the message the text is from.
+fn command_text<'a>(msg: &'a Message, args: &'a str) -> Option<(&'a Message, &'a str)> {
+    if args.trim().is_empty() {
+        let replied = msg.reply_to_message()?;
+        Some((replied, replied.text()?))
+    } else {
+        Some((msg, args))
+    }
+}
+
+/// Lists every unit in the catalog, grouped by metric, with its shortest alias.
+fn list_units(converter: &Converter) -> String {
+    let mut out = String::from("Recognised units:");
+
+    for metric in Metric::ALL {
+        // Prefix and suffix variants of a currency are separate units sharing a
+        // name, so units are merged by name.
+        let mut units: Vec<(&str, &str)> = Vec::new();
+
+        for unit in converter
+            .catalog()
+            .units()
+            .iter()
+            .filter(|unit| unit.metric == metric)
+        {
+            for alias in &unit.aliases {
+                match units.iter_mut().find(|(name, _)| *name == unit.name) {
+                    Some((_, shortest)) if alias.chars().count() < shortest.chars().count() => {
+                        *shortest = alias;
+                    }
+                    Some(_) => {}
+                    None => units.push((&unit.name, alias)),
+                }
+            }
+        }
+
+        if units.is_empty() {
+            continue;
+        }
+
+        let listed: Vec<String> = units
+            .into_iter()
+            .map(|(name, alias)| {
+                if alias.replace(' ', "") == name.replace(' ', "") {
+                    name.to_owned()
+                } else {
+                    format!("{name} ({alias})")
+                }
+            })
+            .collect();
+        let _ = write!(out, "\n\n{metric}: {}", listed.join(", "));
+    }
+
+    out
+}
+
+/// Walks through the arithmetic behind every conversion in `text`.
+fn explain(conversions: &[Conversion], text: &str) -> String {
+    if conversions.is_empty() {
+        return "There is nothing to explain, I could not find any amounts.".to_owned();
+    }
+
+    let mut out = String::new();
+
+    for conversion in conversions {
+        if conversion.unit.metric == Metric::Temperature {
+            explain_temperature(&mut out, conversion, text);
+            continue;
+        }
+
+        let metric = conversion.metric();
+        let (figures, joiner) = figures(conversion);
+        let (amount, bases, unit) = match conversion.per {
+            Some(per) => (
+                join(&figures, joiner, ""),
+                figures
+                    .iter()
+                    .map(|figure| figure * per.factor / conversion.unit.factor)
+                    .collect(),
+                format!("{} per {}", conversion.unit.plural, per.name),
+            ),
+            // Dimensions, whose amount is the product of their edges
+            None if !conversion.edges.is_empty() => (
+                join(&conversion.edges, " × ", ""),
+                vec![conversion
+                    .edges
+                    .iter()
+                    .map(|edge| edge / conversion.unit.factor)
+                    .product()],
+                conversion.unit.plural.clone(),
+            ),
+            None => (
+                join(&figures, joiner, ""),
+                figures
+                    .iter()
+                    .map(|figure| figure / conversion.unit.factor)
+                    .collect::<Vec<_>>(),
+                conversion.unit.plural.clone(),
+            ),
+        };
+        let base_unit = metric.base_unit();
+        let reference = format_significant(conversion.reference.value(metric).unwrap_or(f64::NAN));
+        let divisions: Vec<String> = bases
+            .iter()
+            .map(|&base| {
+                format!(
+                    "{} {base_unit} / {reference} {base_unit}",
+                    format_significant(base)
+                )
+            })
+            .collect();
+
+        let _ = writeln!(
+            out,
+            "\"{}\" is {} {} = {}, and one {}{} is {reference} {base_unit}, so it is {} = {}.",
+            &text[conversion.span.clone()],
+            amount,
+            unit,
+            join(&bases, joiner, &format!(" {base_unit}")),
+            conversion.reference.name,
+            match metric {
+                Metric::Time => " battery life",
+                Metric::Speed => " per second",
+                Metric::Data => " storage",
+                Metric::DataRate => " storage per hour",
+                Metric::Power => " charger",
+                _ => "",
+            },
+            divisions.join(joiner),
+            conversion,
+        );
+    }
+
+    out
+}
+
+/// The figures `conversion` was written with, which are both ends of a range
+/// and the nominal figure and tolerance of a tolerance, and how to join them.
+fn figures(conversion: &Conversion) -> (Vec<f64>, &'static str) {
+    match conversion.precision {
+        Precision::Exact | Precision::Approximate => (vec![conversion.amount], ""),
+        Precision::Range { amount, .. } => (vec![conversion.amount, amount], " to "),
+        Precision::Tolerance { amount, .. } => (vec![conversion.amount, amount], " ± "),
+    }
+}
+
+/// Formats `figures` with `suffix` after each, separated by `joiner`.
+fn join(figures: &[f64], joiner: &str, suffix: &str) -> String {
+    figures
+        .iter()
+        .map(|&figure| format!("{}{suffix}", format_significant(figure)))
+        .collect::<Vec<_>>()
+        .join(joiner)
+}
+
+/// Temperatures are compared to the reference's operating range rather than
+/// divided by a single value, so they are explained separately.
+fn explain_temperature(out: &mut String, conversion: &Conversion, text: &str) {
+    let unit = conversion.unit;
+    let reference = conversion.reference;
+    let (low, high) = reference.temperature.unwrap_or((f64::NAN, f64::NAN));
+    let width = format_significant(high - low);
+    let matched = &text[conversion.span.clone()];
+    let (figures, joiner) = figures(conversion);
+    let amount = join(&figures, joiner, "");
+
+    // The tolerance of a reading is a change around it, like a difference
+    let is_change = |idx: usize| {
+        conversion.difference
+            || idx > 0 && matches!(conversion.precision, Precision::Tolerance { .. })
+    };
+    let bases: Vec<f64> = figures
+        .iter()
+        .enumerate()
+        .map(|(idx, figure)| {
+            if is_change(idx) {
+                figure / unit.factor
+            } else {
+                figure / unit.factor + unit.offset
+            }
+        })
+        .collect();
+    let base = join(&bases, joiner, " K");
+    let positions: Vec<String> = bases
+        .iter()
+        .enumerate()
+        .map(|(idx, &base)| {
+            if is_change(idx) {
+                format!("{} K / {width} K", format_significant(base))
+            } else {
+                format!(
+                    "({} K - {} K) / {width} K",
+                    format_significant(base),
+                    format_significant(low)
+                )
+            }
+        })
+        .collect();
+    let positions = positions.join(joiner);
+
+    if conversion.difference {
+        let _ = writeln!(
+            out,
+            "\"{matched}\" is a change of {amount} {} = {base}, and a {} is rated for a range {width} K wide, so it is {positions} = {conversion}.",
+            unit.plural,
+            reference.name,
+        );
+    } else {
+        let values = match conversion.precision {
+            Precision::Range { value, .. } | Precision::Tolerance { value, .. } => {
+                join(&[conversion.value, value], joiner, "")
+            }
+            Precision::Exact | Precision::Approximate => format_significant(conversion.value),
+        };
+        let _ = writeln!(
+            out,
+            "\"{matched}\" is {amount} {} = {base}, and a {} is rated to operate between {} K and {} K, so it is {positions} = {values}, or {conversion}.",
+            unit.plural,
+            reference.name,
+            format_significant(low),
+            format_significant(high),
+        );
+    }
+}
+
+/// A single change requested with `/settings`.
+enum SettingChange {
+    Reset,
+    Reference(&'static ReferenceObject),
+    Style(OutputStyle),
+    Mode(Mode),
+    Enable(Metric),
+    Disable(Metric),
+    Prefer(Option<Metric>),
+    Skip(EntityKind),
+    Unskip(EntityKind),
+    Locale(Locale),
+}
+
+impl SettingChange {
+    fn parse(setting: &str, value: &str) -> Result<Self, String> {
+        match (setting, value) {
+            ("reset", "") => Ok(SettingChange::Reset),
+            ("reference", name) => find_reference(name)
+                .map(SettingChange::Reference)
+                .ok_or_else(|| {
+                    format!(
+                        "Unknown reference object `{name}`, choose one of: {}",
+                        REFERENCE_OBJECTS.map(|reference| reference.name).join(", ")
+                    )
+                }),
+            ("style", style) => style.parse().map(SettingChange::Style),
+            ("mode", mode) => mode.parse().map(SettingChange::Mode),
+            ("enable", metric) => metric.parse().map(SettingChange::Enable),
+            ("disable", metric) => metric.parse().map(SettingChange::Disable),
+            ("skip", kind) => kind.parse().map(SettingChange::Skip),
+            ("unskip", kind) => kind.parse().map(SettingChange::Unskip),
+            ("locale", locale) => locale.parse().map(SettingChange::Locale),
+            ("prefer", "none") => Ok(SettingChange::Prefer(None)),
+            ("prefer", metric) => metric
+                .parse()
+                .map(|metric| SettingChange::Prefer(Some(metric))),
+            _ => Err(SETTINGS_USAGE.to_owned()),
+        }
+    }
+
+    fn apply(self, settings: &mut ChatSettings) {
+        match self {
+            SettingChange::Reset => *settings = ChatSettings::default(),
+            SettingChange::Reference(reference) => {
+                reference.name.clone_into(&mut settings.reference);
+            }
+            SettingChange::Style(style) => settings.style = style,
+            SettingChange::Mode(mode) => settings.mode = mode,
+            SettingChange::Enable(metric) => settings.set_converted(metric, true),
+            SettingChange::Disable(metric) => settings.set_converted(metric, false),
+            SettingChange::Skip(kind) => settings.set_skipped(kind, true),
+            SettingChange::Unskip(kind) => settings.set_skipped(kind, false),
+            SettingChange::Locale(locale) => settings.locale = locale,
+            SettingChange::Prefer(None) => settings.prefer.clear(),
+            // The latest preference wins over earlier ones
+            SettingChange::Prefer(Some(metric)) => {
+                settings.prefer.retain(|preferred| *preferred != metric);
+                settings.prefer.insert(0, metric);
+            }
+        }
+    }
+}
+
+/// Applies a `/settings` command and describes the outcome.
+async fn change_settings(settings: &SettingsStore, chat: ChatId, args: &str) -> String {
+    let mut args = args.split_whitespace();
+    let Some(setting) = args.next() else {
+        return format!("Settings for this chat:\n{}", settings.get(chat));
+    };
+    let value = args.collect::<Vec<_>>().join(" ");
+
+    let change = match SettingChange::parse(setting, &value) {
+        Ok(change) => change,
+        Err(err) => return err,
+    };
+
+    match settings
+        .update(chat, |settings| change.apply(settings))
+        .await
+    {
+        Ok(updated) => format!("Settings updated:\n{updated}"),
+        Err(err) => {
+            eprintln!("Could not save settings of chat {chat}: {err}");
+            "Could not save the settings, please try again later.".to_owned()
+        }
+    }
+}
@@ -0,0 +1,1124 @@
+use std::{
+    fmt::{self, Write},
+    ops::Range,
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ConversionError,
+    matcher::Matcher,
+    matcher::{in_number, lex_amount},
+    models::format_count,
+    normalize::Normalized,
+    resolve::resolve,
+    reverse::{self, ReverseAnswer},
+    Catalog, Metric, ReferenceObject, Unit,
+};
+
+/// A word that may join the parts of a mixed quantity, as in "1 hour and 20
+/// minutes".
+const AND: &str = "and";
+
+/// Words right before an amount that make it an estimate, as in "~200 g" or
+/// "around $50".
+const APPROXIMATE_PREFIXES: [&str; 9] = [
+    "~",
+    "≈",
+    "about",
+    "around",
+    "approx",
+    "approx.",
+    "approximately",
+    "roughly",
+    "circa",
+];
+
+/// Words between the two ends of a range, as in "5-10 kg" or "3 to 4 km".
+const RANGE_SEPARATORS: [&str; 4] = ["-", "–", "—", "to"];
+
+/// The word before the lower end of a range written as "between 3 and 4 km".
+const BETWEEN: &str = "between";
+
+/// Signs between the edges of dimensions, as in "2 m x 3 m" or "2 by 3 m".
+const DIMENSION_SIGNS: [&str; 3] = ["x", "×", BY];
+
+/// The one sign between edges that never means counting, as "x" does in
+/// "4 x 100 m".
+const BY: &str = "by";
+
+/// How many times a whole number a length following it after "x" has to be
+/// for the number to count lengths, as in "4 x 100 m", rather than be an edge.
+const COUNT_RATIO: f64 = 10.0;
+
+/// Signs between an amount and its tolerance, as in "5±0.2 mm".
+const TOLERANCE_SIGNS: [&str; 2] = ["±", "+/-"];
+
+/// Words right before a temperature that make it a change rather than a
+/// reading, as in "up by 5 °C".
+const DIFFERENCE_PREFIXES: [&str; 4] = ["+", "±", "Δ", "by"];
+
+/// Words right after a temperature that make it a change rather than a
+/// reading, as in "5 °C warmer".
+const DIFFERENCE_SUFFIXES: [&str; 14] = [
+    "warmer",
+    "colder",
+    "hotter",
+    "cooler",
+    "higher",
+    "lower",
+    "more",
+    "less",
+    "increase",
+    "decrease",
+    "rise",
+    "drop",
+    "difference",
+    "change",
+];
+
+/// Words after a temperature placing it relative to a point on the scale,
+/// and whether they put it under that point, as in "10 °C below zero".
+const SIDES: [(&str, bool); 2] = [("below", true), ("above", false)];
+
+/// The point "below zero" and "above zero" readings are relative to.
+const ZERO: &str = "zero";
+
+/// A point whose distance "below freezing" or "above freezing" is a change
+/// in temperature rather than a reading.
+const FREEZING: &str = "freezing";
+
+/// A single amount found in a piece of text, together with its value in terms
+/// of a reference object.
+#[derive(Debug, Clone)]
+pub struct Conversion<'a> {
+    /// Byte range of the matched amount and unit in the original text.
+    pub span: Range<usize>,
+    /// The parsed amount, expressed in `unit`.
+    pub amount: f64,
+    /// The unit the amount was written in, or the smallest one for a mixed
+    /// quantity like "5 ft 11 in".
+    pub unit: &'a Unit,
+    /// The duration `unit` is divided by when the amount is a rate written
+    /// with a compound unit such as `km/h` or `MB/s`.
+    pub per: Option<&'a Unit>,
+    /// The object the amount was expressed in.
+    pub reference: &'a ReferenceObject,
+    /// The amount expressed in multiples of `reference`.
+    pub value: f64,
+    /// Whether the amount is a difference between two temperatures rather
+    /// than a temperature. Always `false` for other metrics.
+    pub difference: bool,
+    /// Whether the amount was written as an estimate, a range or with a
+    /// tolerance rather than as an exact figure.
+    pub precision: Precision,
+    /// The length of every edge in `unit` when the amount is an area or volume
+    /// written as dimensions, like "2m x 3m" or "40 x 30 x 20 cm". The amount
+    /// is then their product. Empty for any other amount.
+    pub edges: Vec<f64>,
+}
+
+/// How exactly an amount was given.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Precision {
+    /// A single figure, like "200 g".
+    Exact,
+    /// An estimate, like "~200 g" or "around $50".
+    Approximate,
+    /// A range, like "5-10 kg" or "between 3 and 4 km". The conversion's
+    /// amount and value are its lower end, these are its upper end.
+    Range { amount: f64, value: f64 },
+    /// An amount give or take a tolerance, like "5±0.2 mm". The conversion's
+    /// amount and value are the nominal figure, these are the tolerance.
+    Tolerance { amount: f64, value: f64 },
+}
+
+/// How converted amounts are presented in the rewritten text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStyle {
+    /// Every amount is replaced by its converted value.
+    #[default]
+    Replace,
+    /// Every amount is kept, followed by its converted value in brackets and,
+    /// for dimensions, by how many objects fit along each edge.
+    Annotate,
+    /// Only the amounts and their converted values are listed, one per line,
+    /// with the edges of dimensions as when annotating.
+    List,
+}
+
+impl OutputStyle {
+    pub const ALL: [OutputStyle; 3] = [
+        OutputStyle::Replace,
+        OutputStyle::Annotate,
+        OutputStyle::List,
+    ];
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputStyle::Replace => "replace",
+            OutputStyle::Annotate => "annotate",
+            OutputStyle::List => "list",
+        }
+    }
+}
+
+impl fmt::Display for OutputStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for OutputStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        OutputStyle::ALL
+            .into_iter()
+            .find(|style| style.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown output style `{s}`"))
+    }
+}
+
+/// How numbers are written: which mark separates the decimals, the other one
+/// grouping thousands.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    /// Told from the number itself: a mark used twice or followed by
+    /// exactly three digits, as in "1,500", groups thousands.
+    #[default]
+    Auto,
+    /// Decimal points, as in "1,234.5".
+    Point,
+    /// Decimal commas, as in "1.234,5".
+    Comma,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 3] = [Locale::Auto, Locale::Point, Locale::Comma];
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Locale::Auto => "auto",
+            Locale::Point => "point",
+            Locale::Comma => "comma",
+        }
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Locale::ALL
+            .into_iter()
+            .find(|locale| locale.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown locale `{s}`"))
+    }
+}
+
+/// Per-chat choices that change how text is converted.
+#[derive(Debug, Clone, Default)]
+pub struct ConvertOptions {
+    /// Metrics to favour, most preferred first, when an alias like "pound"
+    /// belongs to units of several metrics or is only a weak alias, and the
+    /// words around it do not tell which one is meant.
+    pub prefer: Vec<Metric>,
+    /// Byte ranges of the text that are never converted, such as code blocks
+    /// or links.
+    pub skip: Vec<Range<usize>>,
+    /// How numbers are written, as in "1,5 kg" or "1.500,00 €".
+    pub locale: Locale,
+}
+
+impl ConvertOptions {
+    /// Whether `span` overlaps any range that must not be converted.
+    pub(crate) fn skips(&self, span: &Range<usize>) -> bool {
+        self.skip
+            .iter()
+            .any(|skip| skip.start < span.end && span.start < skip.end)
+    }
+}
+
+/// Finds amounts with units in text and converts them into reference objects.
+pub struct Converter {
+    catalog: Catalog,
+    matcher: Matcher,
+}
+
+impl Converter {
+    /// Builds a converter recognising every alias in `catalog`.
+    #[must_use]
+    pub fn new(catalog: Catalog) -> Self {
+        Self {
+            matcher: Matcher::new(catalog.units()),
+            catalog,
+        }
+    }
+
+    #[must_use]
+    pub fn catalog(&self) -> &Catalog {
+        &self.catalog
+    }
+
+    /// Finds every amount in `text` and expresses it in multiples of
+    /// `reference`. Amounts in a metric the reference has no value for are
+    /// left out, as are matches that fail to convert.
+    ///
+    /// Amounts written out in words, such as "twenty five kg", are detected as
+    /// well; their spans cover the words in `text`. The returned conversions
+    /// are sorted by position and never overlap.
+    ///
+    /// Temperatures may be negative, and are read as a difference when the
+    /// surrounding words say so, as in "+5 °C" or "5 °C warmer". Aliases shared
+    /// by several units, like "pound", are resolved from the surrounding words,
+    /// and amounts that likely are not measurements at all, like "1 in 10", are
+    /// left out.
+    ///
+    /// Amounts of the same metric written one after another in ever smaller
+    /// units, like "5 ft 11 in" or "1 h 20 min", are combined into a single
+    /// conversion of their total. Lengths written as dimensions, like "2m x 3m"
+    /// or "40 x 30 x 20 cm", become an area or volume. Ranges like "5-10 kg",
+    /// estimates like "~200 g" and tolerances like "5±0.2 mm" are kept as
+    /// such, see [`Precision`].
+    #[must_use]
+    pub fn convert_text<'a>(
+        &'a self,
+        text: &str,
+        reference: &'a ReferenceObject,
+    ) -> Vec<Conversion<'a>> {
+        self.try_convert_text(text, reference, &ConvertOptions::default())
+            .into_iter()
+            .filter_map(Result::ok)
+            .collect()
+    }
+
+    /// Like [`Converter::convert_text`], but reports every match that failed
+    /// to convert instead of leaving it out, and follows a chat's `options`.
+    #[must_use]
+    pub fn try_convert_text<'a>(
+        &'a self,
+        text: &str,
+        reference: &'a ReferenceObject,
+        options: &ConvertOptions,
+    ) -> Vec<Result<Conversion<'a>, ConversionError>> {
+        let normalized = Normalized::new(text);
+
+        let conversions = self
+            .matcher
+            .find_all(&normalized.text, options.locale)
+            .into_iter()
+            .filter_map(|found| {
+                let mut span = normalized.to_original(&found.span);
+                if options.skips(&span) {
+                    return None;
+                }
+                let matched = |span: &Range<usize>| text[span.clone()].to_owned();
+
+                let units = self.catalog.units();
+                let unit = resolve(
+                    &normalized.text,
+                    &found,
+                    normalized.is_written_out(&found.span),
+                    units,
+                    &options.prefer,
+                )?;
+                let (Some(unit), Ok(per)) = (
+                    units.get(unit),
+                    found.per.map(|per| units.get(per).ok_or(())).transpose(),
+                ) else {
+                    return Some(Err(ConversionError::UnknownUnit(matched(&span))));
+                };
+                let Ok(mut amount) = found.amount else {
+                    return Some(Err(ConversionError::InvalidAmount(matched(&span))));
+                };
+
+                let mut difference = false;
+                if unit.metric == Metric::Temperature {
+                    if let Some(start) = negative_sign(text, span.start) {
+                        span.start = start;
+                        amount = -amount;
+                    }
+                    // "10 °C below zero" is the reading -10 °C
+                    if let Some((end, below)) = side_of(text, span.end, ZERO) {
+                        span.end = end;
+                        if below {
+                            amount = -amount;
+                        }
+                    }
+                    difference = is_difference(text, &span);
+                }
+
+                let value = reference_value(unit, per, amount, reference, difference)?;
+
+                if !amount.is_finite() || !value.is_finite() {
+                    return Some(Err(ConversionError::OutOfRange(matched(&span))));
+                }
+
+                Some(Ok(Conversion {
+                    span,
+                    amount,
+                    unit,
+                    per,
+                    reference,
+                    value,
+                    difference,
+                    precision: Precision::Exact,
+                    edges: Vec::new(),
+                }))
+            })
+            .collect();
+
+        let conversions = combine_dimensions(text, combine_mixed(text, conversions), options);
+        qualify_all(text, conversions, options)
+    }
+
+    /// Answers every query like "12 davincis in kg" or "3.5 davincis to usd" in
+    /// `text`, in order, except in the ranges `options` skips. Queries naming a
+    /// unit that does not exist, or one the reference object has no value for,
+    /// yield an error instead.
+    #[must_use]
+    pub fn convert_reverse(&self, text: &str, options: &ConvertOptions) -> Vec<ReverseAnswer<'_>> {
+        reverse::convert_reverse(&self.matcher, self.catalog.units(), text, options)
+    }
+}
+
+impl Default for Converter {
+    fn default() -> Self {
+        Self::new(Catalog::default())
+    }
+}
+
+/// Merges every run of conversions making up a mixed quantity, like "5 ft 11
+/// in", into one conversion spanning the whole run.
+fn combine_mixed<'a>(
+    text: &str,
+    conversions: Vec<Result<Conversion<'a>, ConversionError>>,
+) -> Vec<Result<Conversion<'a>, ConversionError>> {
+    let mut combined: Vec<Result<Conversion<'a>, ConversionError>> =
+        Vec::with_capacity(conversions.len());
+
+    for conversion in conversions {
+        if let (Some(Ok(larger)), Ok(smaller)) = (combined.last_mut(), &conversion) {
+            if continues_mixed(text, larger, smaller) {
+                larger.span.end = smaller.span.end;
+                larger.amount =
+                    larger.amount * smaller.unit.factor / larger.unit.factor + smaller.amount;
+                larger.unit = smaller.unit;
+                larger.value += smaller.value;
+                continue;
+            }
+        }
+        combined.push(conversion);
+    }
+
+    combined
+}
+
+/// Whether `smaller` directly follows `larger` as the next part of a mixed
+/// quantity: an amount of the same metric in a smaller unit, separated only
+/// by whitespace or "and". Only metrics that add up plainly qualify, so
+/// prices in two currencies or temperatures are never combined.
+fn continues_mixed(text: &str, larger: &Conversion, smaller: &Conversion) -> bool {
+    let gap = text[larger.span.end..smaller.span.start].trim();
+
+    (gap.is_empty() || gap.eq_ignore_ascii_case(AND))
+        && larger.per.is_none()
+        && smaller.per.is_none()
+        && larger.unit.metric == smaller.unit.metric
+        && !matches!(larger.unit.metric, Metric::Currency | Metric::Temperature)
+        && smaller.unit.factor > larger.unit.factor
+}
+
+/// Turns lengths written as dimensions, like "2m x 3m", "2 by 3 m" or "40 x 30
+/// x 20 cm", into one area or volume conversion each.
+fn combine_dimensions<'a>(
+    text: &str,
+    conversions: Vec<Result<Conversion<'a>, ConversionError>>,
+    options: &ConvertOptions,
+) -> Vec<Result<Conversion<'a>, ConversionError>> {
+    let mut combined: Vec<Result<Conversion<'a>, ConversionError>> =
+        Vec::with_capacity(conversions.len());
+
+    for conversion in conversions {
+        let floor = previous_end(&combined);
+        let conversion =
+            conversion.map(|conversion| with_bare_edges(text, conversion, floor, options));
+
+        // Every edge written with a unit, as in "2 m x 3 m"
+        if let (Some(Ok(first)), Ok(next)) = (combined.last_mut(), &conversion) {
+            if let Some(dimensions) = continue_dimensions(text, first, next) {
+                *first = dimensions;
+                continue;
+            }
+        }
+        combined.push(conversion);
+    }
+
+    combined
+}
+
+/// `first` and `next` as one area or volume, if `next` is a length directly
+/// following `first` after a sign like "x".
+fn continue_dimensions<'a>(
+    text: &str,
+    first: &Conversion<'a>,
+    next: &Conversion<'a>,
+) -> Option<Conversion<'a>> {
+    if !is_edge(first) || !is_edge(next) || !next.edges.is_empty() || first.edges.len() > 2 {
+        return None;
+    }
+
+    let gap = text[first.span.end..next.span.start].trim();
+    DIMENSION_SIGNS
+        .iter()
+        .any(|sign| gap.eq_ignore_ascii_case(sign))
+        .then_some(())?;
+
+    let mut edges: Vec<f64> = if first.edges.is_empty() {
+        vec![first.amount]
+    } else {
+        first.edges.clone()
+    };
+    for edge in &mut edges {
+        *edge *= next.unit.factor / first.unit.factor;
+    }
+    edges.push(next.amount);
+
+    with_edges(first.span.start..next.span.end, next, edges)
+}
+
+/// `conversion` with the bare amounts written before it as more edges, as
+/// the "40 x 30" of "40 x 30 x 20 cm", which are in the same unit. Like
+/// [`qualify`], nothing before `floor` or in a range `options` skips is taken
+/// in.
+///
+/// A single whole number times a length at least [`COUNT_RATIO`] times as
+/// large, like the "4 x 100 m" of a relay, counts lengths rather than giving
+/// an area, and is left for the length to be converted alone.
+fn with_bare_edges<'a>(
+    text: &str,
+    conversion: Conversion<'a>,
+    floor: usize,
+    options: &ConvertOptions,
+) -> Conversion<'a> {
+    if !is_edge(&conversion) {
+        return conversion;
+    }
+
+    let mut start = conversion.span.start;
+    let mut edges = vec![conversion.amount];
+    let mut times = false;
+    while edges.len() < 3 {
+        let before = text[..start].trim_end();
+        let Some((edge, edge_start, sign)) = DIMENSION_SIGNS
+            .iter()
+            .find_map(|sign| {
+                let rest = before.get(..before.len().checked_sub(sign.len())?)?;
+                before[rest.len()..]
+                    .eq_ignore_ascii_case(sign)
+                    .then_some((rest, sign))
+            })
+            // "2x3 m" has no space around the "x", but "box" is no sign
+            .filter(|(rest, _)| !rest.ends_with(char::is_alphabetic))
+            .and_then(|(rest, sign)| {
+                let (edge, edge_start) =
+                    amount_before(text, floor, rest.trim_end().len(), options.locale)?;
+                Some((edge, edge_start, sign))
+            })
+            .filter(|&(_, edge_start, _)| !options.skips(&(edge_start..conversion.span.start)))
+        else {
+            break;
+        };
+
+        edges.insert(0, edge);
+        start = edge_start;
+        times = *sign != BY;
+    }
+
+    match edges[..] {
+        [_] => return conversion,
+        [count, length] if times && count.fract() == 0.0 && length >= COUNT_RATIO * count => {
+            return conversion;
+        }
+        _ => {}
+    }
+    with_edges(start..conversion.span.end, &conversion, edges).unwrap_or(conversion)
+}
+
+/// Whether `conversion` may be an edge of dimensions: a plain length.
+fn is_edge(conversion: &Conversion) -> bool {
+    conversion.unit.metric == Metric::Length
+        && conversion.per.is_none()
+        && conversion.precision == Precision::Exact
+}
+
+/// `conversion` as the area or volume spanning `span` with `edges` in its
+/// unit, unless the reference has no value for it.
+fn with_edges<'a>(
+    span: Range<usize>,
+    conversion: &Conversion<'a>,
+    edges: Vec<f64>,
+) -> Option<Conversion<'a>> {
+    let metric = match edges.len() {
+        2 => Metric::Area,
+        3 => Metric::Volume,
+        _ => return None,
+    };
+    let amount: f64 = edges.iter().product();
+    let base: f64 = edges
+        .iter()
+        .map(|edge| edge / conversion.unit.factor)
+        .product();
+    let value = base / conversion.reference.value(metric)?;
+
+    (amount.is_finite() && value.is_finite()).then(|| Conversion {
+        span,
+        amount,
+        value,
+        edges,
+        ..conversion.clone()
+    })
+}
+
+/// Expresses `amount` of `unit`, divided by `per` for a rate, in multiples of
+/// `reference`.
+fn reference_value(
+    unit: &Unit,
+    per: Option<&Unit>,
+    amount: f64,
+    reference: &ReferenceObject,
+    difference: bool,
+) -> Option<f64> {
+    if difference {
+        unit.difference_in_reference(amount, reference)
+    } else if let Some(per) = per {
+        let metric = unit.metric.per_time()?;
+        Some(amount * per.factor / unit.factor / reference.value(metric)?)
+    } else {
+        unit.in_reference(amount, reference)
+    }
+}
+
+/// Turns conversions written as ranges, estimates or with a tolerance into
+/// one conversion each, widened to cover the whole expression.
+fn qualify_all<'a>(
+    text: &str,
+    conversions: Vec<Result<Conversion<'a>, ConversionError>>,
+    options: &ConvertOptions,
+) -> Vec<Result<Conversion<'a>, ConversionError>> {
+    let mut qualified: Vec<Result<Conversion<'a>, ConversionError>> =
+        Vec::with_capacity(conversions.len());
+
+    for conversion in conversions {
+        // Both ends written with a unit, as in "5 kg - 10 kg"
+        if let (Some(Ok(low)), Ok(high)) = (qualified.last_mut(), &conversion) {
+            if let Some(start) = range_start(text, low, high) {
+                low.span = start..high.span.end;
+                low.precision = Precision::Range {
+                    amount: high.amount,
+                    value: high.value,
+                };
+                continue;
+            }
+        }
+
+        let floor = previous_end(&qualified);
+        qualified.push(conversion.map(|conversion| qualify(text, conversion, floor, options)));
+    }
+
+    qualified
+}
+
+/// Where the range from `low` to `high` starts, if the two conversions are the
+/// ends of one like "5 kg - 10 kg" or "between 3 km and 4 km".
+fn range_start(text: &str, low: &Conversion, high: &Conversion) -> Option<usize> {
+    let same_unit = std::ptr::eq(low.unit, high.unit)
+        && low.per.map(std::ptr::from_ref) == high.per.map(std::ptr::from_ref)
+        && low.difference == high.difference
+        && low.edges.len() == high.edges.len();
+    if !same_unit || low.precision != Precision::Exact || low.span.end > high.span.start {
+        return None;
+    }
+
+    let gap = text[low.span.end..high.span.start].trim();
+    if RANGE_SEPARATORS
+        .iter()
+        .any(|separator| gap.eq_ignore_ascii_case(separator))
+    {
+        return Some(low.span.start);
+    }
+
+    gap.eq_ignore_ascii_case(AND)
+        .then(|| strip_word(text[..low.span.start].trim_end(), BETWEEN))
+        .flatten()
+        .map(str::len)
+}
+
+/// Where the last successful conversion in `conversions` ends, before which
+/// no other conversion may be widened.
+fn previous_end(conversions: &[Result<Conversion, ConversionError>]) -> usize {
+    conversions
+        .iter()
+        .rev()
+        .find_map(|conversion| conversion.as_ref().ok())
+        .map_or(0, |conversion| conversion.span.end)
+}
+
+/// Reads a bare amount written before `conversion` as the other end of a
+/// range or as its nominal figure, as in "5-10 kg" or "5±0.2 mm", or else an
+/// estimate marker like "~".
+///
+/// Nothing before `floor`, where the previous conversion ends, or in a range
+/// `options` skips is taken in, so the "5" of "$5-10 kg" stays a price.
+fn qualify<'a>(
+    text: &str,
+    conversion: Conversion<'a>,
+    floor: usize,
+    options: &ConvertOptions,
+) -> Conversion<'a> {
+    let before = text[..conversion.span.start].trim_end();
+    let plain = conversion.edges.is_empty();
+    let locale = options.locale;
+    let fits = |qualified: &Conversion| {
+        qualified.span.start >= floor
+            && !options.skips(&(qualified.span.start..conversion.span.start))
+    };
+
+    let tolerance = TOLERANCE_SIGNS
+        .iter()
+        .find_map(|sign| before.strip_suffix(sign))
+        .filter(|_| plain)
+        .and_then(|rest| amount_before(text, floor, rest.trim_end().len(), locale))
+        .and_then(|(nominal, start)| with_tolerance(text, &conversion, nominal, start))
+        .filter(fits);
+    if let Some(tolerance) = tolerance {
+        return tolerance;
+    }
+
+    let range = RANGE_SEPARATORS
+        .iter()
+        .find_map(|separator| strip_word(before, separator))
+        .filter(|_| plain)
+        .and_then(|rest| amount_before(text, floor, rest.trim_end().len(), locale))
+        .or_else(|| {
+            let rest = strip_word(before, AND).filter(|_| plain)?;
+            let (mut low, mut start) = amount_before(text, floor, rest.trim_end().len(), locale)?;
+            // The sign of "between -5 and 10 °C" comes after "between"
+            if conversion.unit.metric == Metric::Temperature {
+                if let Some(sign) = negative_sign(text, start) {
+                    start = sign;
+                    low = -low;
+                }
+            }
+            let between = strip_word(text[..start].trim_end(), BETWEEN)?;
+            Some((low, between.len()))
+        })
+        .and_then(|(low, start)| with_lower_end(text, &conversion, low, start))
+        .filter(fits);
+    if let Some(range) = range {
+        return range;
+    }
+
+    match APPROXIMATE_PREFIXES
+        .iter()
+        .find_map(|prefix| strip_word(before, prefix))
+    {
+        Some(rest)
+            if rest.len() >= floor && !options.skips(&(rest.len()..conversion.span.start)) =>
+        {
+            Conversion {
+                span: rest.len()..conversion.span.end,
+                precision: Precision::Approximate,
+                ..conversion
+            }
+        }
+        _ => conversion,
+    }
+}
+
+/// `conversion` as the upper end of a range starting with `low` at `start`.
+fn with_lower_end<'a>(
+    text: &str,
+    conversion: &Conversion<'a>,
+    mut low: f64,
+    mut start: usize,
+) -> Option<Conversion<'a>> {
+    if conversion.unit.metric == Metric::Temperature {
+        if let Some(sign) = negative_sign(text, start) {
+            start = sign;
+            low = -low;
+        }
+    }
+
+    let span = start..conversion.span.end;
+    let difference = conversion.unit.metric == Metric::Temperature && is_difference(text, &span);
+    let value = |amount| {
+        reference_value(
+            conversion.unit,
+            conversion.per,
+            amount,
+            conversion.reference,
+            difference,
+        )
+        .filter(|value| value.is_finite())
+    };
+
+    Some(Conversion {
+        span,
+        amount: low,
+        value: value(low)?,
+        difference,
+        precision: Precision::Range {
+            amount: conversion.amount,
+            value: value(conversion.amount)?,
+        },
+        ..conversion.clone()
+    })
+}
+
+/// `conversion` as the tolerance of a `nominal` amount written at `start`.
+fn with_tolerance<'a>(
+    text: &str,
+    conversion: &Conversion<'a>,
+    nominal: f64,
+    start: usize,
+) -> Option<Conversion<'a>> {
+    let span = start..conversion.span.end;
+    let temperature = conversion.unit.metric == Metric::Temperature;
+    let difference = temperature && is_difference(text, &span);
+    let value = |amount, difference| {
+        reference_value(
+            conversion.unit,
+            conversion.per,
+            amount,
+            conversion.reference,
+            difference,
+        )
+        .filter(|value| value.is_finite())
+    };
+
+    Some(Conversion {
+        span,
+        amount: nominal,
+        value: value(nominal, difference)?,
+        difference,
+        precision: Precision::Tolerance {
+            amount: conversion.amount,
+            // A tolerance of a temperature is always a difference
+            value: value(conversion.amount, temperature)?,
+        },
+        ..conversion.clone()
+    })
+}
+
+/// Returns the value and start of the bare amount ending exactly at `end`,
+/// written the way `locale` says, unless it is part of a longer word or
+/// starts before `floor`.
+fn amount_before(text: &str, floor: usize, end: usize, locale: Locale) -> Option<(f64, usize)> {
+    let run = floor + text.get(floor..end)?.trim_end_matches(in_number).len();
+    if text[..run]
+        .chars()
+        .next_back()
+        .is_some_and(char::is_alphanumeric)
+    {
+        return None;
+    }
+
+    text[run..end]
+        .char_indices()
+        .map(|(offset, _)| run + offset)
+        .find_map(|start| match lex_amount(text, start, locale)? {
+            (Ok(amount), amount_end) if amount_end == end => Some((amount, start)),
+            _ => None,
+        })
+}
+
+/// Strips `word` from the end of `text`, ignoring case. A word starting with a
+/// letter must not be the end of a longer word.
+fn strip_word<'t>(text: &'t str, word: &str) -> Option<&'t str> {
+    let start = text.len().checked_sub(word.len())?;
+    let rest = text
+        .get(start..)
+        .filter(|end| end.eq_ignore_ascii_case(word))
+        .map(|_| &text[..start])?;
+
+    (!word.starts_with(char::is_alphanumeric) || !rest.ends_with(char::is_alphanumeric))
+        .then_some(rest)
+}
+
+/// Returns where a minus sign directly in front of the amount starting at
+/// `start` begins, unless it is a hyphen joining the amount to a word.
+fn negative_sign(text: &str, start: usize) -> Option<usize> {
+    let before = &text[..start];
+    let sign = before.strip_suffix(['-', '−'])?;
+
+    (!sign.chars().next_back().is_some_and(char::is_alphanumeric)).then_some(sign.len())
+}
+
+/// Whether the temperature at `span` is a change in temperature, judging by
+/// the word before and after it.
+fn is_difference(text: &str, span: &Range<usize>) -> bool {
+    let before = text[..span.start].trim_end();
+    let after = text[span.end..].trim_start();
+    let word_after = after
+        .split(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or_default();
+
+    DIFFERENCE_PREFIXES
+        .iter()
+        .any(|prefix| strip_word(before, prefix).is_some())
+        || DIFFERENCE_SUFFIXES
+            .iter()
+            .any(|suffix| word_after.eq_ignore_ascii_case(suffix))
+        || side_of(text, span.end, FREEZING).is_some()
+}
+
+/// If the amount ending at `end` is followed by "below" or "above" `point`,
+/// returns where those words end and whether the amount is below it.
+fn side_of(text: &str, end: usize, point: &str) -> Option<(usize, bool)> {
+    SIDES.iter().find_map(|&(side, below)| {
+        let rest = word_prefix(text[end..].trim_start(), side)?;
+        let rest = word_prefix(rest.trim_start(), point)?;
+        Some((text.len() - rest.len(), below))
+    })
+}
+
+/// `text` after `word` if it starts with it, ignoring ASCII case, followed by
+/// a word boundary.
+fn word_prefix<'t>(text: &'t str, word: &str) -> Option<&'t str> {
+    text.get(..word.len())
+        .filter(|found| found.eq_ignore_ascii_case(word))
+        .map(|_| &text[word.len()..])
+        .filter(|rest| !rest.starts_with(char::is_alphanumeric))
+}
+
+/// Formats a number with four significant digits, without trailing zeros.
+#[must_use]
+pub fn format_significant(value: f64) -> String {
+    if value == 0.0 || !value.is_finite() {
+        return value.to_string();
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let decimals = (3 - value.abs().log10().floor() as i32).max(0) as usize;
+    let formatted = format!("{value:.decimals$}");
+
+    if formatted.contains('.') {
+        formatted
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_owned()
+    } else {
+        formatted
+    }
+}
+
+impl Conversion<'_> {
+    /// The metric of the amount, which for compound units is a rate like
+    /// speed even though `unit` is a length.
+    #[must_use]
+    pub fn metric(&self) -> Metric {
+        match self.edges.len() {
+            2 => Metric::Area,
+            3 => Metric::Volume,
+            _ => self
+                .per
+                .and_then(|_| self.unit.metric.per_time())
+                .unwrap_or(self.unit.metric),
+        }
+    }
+
+    /// Formats `value` of this conversion's metric, a difference between two
+    /// temperatures if `difference` is set.
+    fn format_value(&self, value: f64, difference: bool) -> String {
+        match self.metric() {
+            Metric::Temperature => self.reference.format_temperature(value, difference),
+            Metric::Time => self.reference.format_time(value),
+            Metric::Speed => self.reference.format_speed(value),
+            Metric::Data => self.reference.format_storage(value),
+            Metric::DataRate => self.reference.format_storage_rate(value),
+            Metric::Power => self.reference.format_power(value),
+            _ => self.reference.format(value),
+        }
+    }
+
+    /// Like [`Conversion::format_value`], but with four significant digits
+    /// like the other end of a range, so that close ends stay apart.
+    fn format_figure(&self, value: f64) -> String {
+        let formatted = self.format_value(value, self.difference);
+        // Every formatted value starts with its figure
+        let names = formatted.split_once(' ').map_or("", |(_, names)| names);
+
+        format!("{} {names}", format_significant(value))
+    }
+}
+
+impl fmt::Display for Conversion<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let formatted = self.format_value(self.value, self.difference);
+        // Temperatures are placed in the operating range in words, so both
+        // ends are written out in full.
+        let reading = self.metric() == Metric::Temperature && !self.difference;
+
+        match self.precision {
+            Precision::Exact => f.write_str(&formatted),
+            Precision::Approximate => write!(f, "~{formatted}"),
+            Precision::Range { value, .. } if reading => {
+                write!(f, "{formatted} to {}", self.format_value(value, false))
+            }
+            Precision::Range { value, .. } => write!(
+                f,
+                "{}–{}",
+                format_significant(self.value),
+                self.format_figure(value)
+            ),
+            Precision::Tolerance { value, .. } if reading => {
+                write!(f, "{formatted} ± {}", self.format_value(value, true))
+            }
+            Precision::Tolerance { value, .. } => write!(
+                f,
+                "{}±{}",
+                format_significant(self.value),
+                self.format_figure(value)
+            ),
+        }?;
+
+        // The alternate form also tells how many objects fit along each edge
+        if f.alternate() && !self.edges.is_empty() {
+            let edges: Vec<String> = self
+                .edges
+                .iter()
+                .map(|&edge| {
+                    let value = self.unit.in_reference(edge, self.reference);
+                    format_count(value.unwrap_or(f64::NAN))
+                })
+                .collect();
+            write!(
+                f,
+                ", {} {} along the edges",
+                edges.join(" × "),
+                self.reference.plural
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Text with its conversions written in some [`OutputStyle`], remembering
+/// where each one went so that ranges of the original text, like its
+/// formatting, can be carried over.
+#[derive(Debug, Clone)]
+pub struct Rewritten {
+    pub text: String,
+    /// `None` when the text does not follow the original, as for a list.
+    edits: Option<Vec<Edit>>,
+}
+
+/// One conversion's span in the original text and its replacement's in the
+/// rewritten text.
+#[derive(Debug, Clone)]
+struct Edit {
+    original: Range<usize>,
+    rewritten: Range<usize>,
+}
+
+impl Rewritten {
+    /// Rewrites `text` in `style`. `conversions` must be sorted and
+    /// non-overlapping, as returned by [`Converter::convert_text`].
+    #[must_use]
+    pub fn new(text: &str, conversions: &[Conversion], style: OutputStyle) -> Self {
+        if style == OutputStyle::List {
+            let lines: Vec<String> = conversions
+                .iter()
+                .map(|conversion| format!("• {}: {conversion:#}", &text[conversion.span.clone()]))
+                .collect();
+
+            return Self {
+                text: lines.join("\n"),
+                edits: None,
+            };
+        }
+
+        let mut out = String::with_capacity(text.len());
+        let mut edits = Vec::with_capacity(conversions.len());
+        let mut last = 0;
+
+        for conversion in conversions {
+            out.push_str(&text[last..conversion.span.start]);
+            let start = out.len();
+            if style == OutputStyle::Annotate {
+                let _ = write!(out, "{} ({conversion:#})", &text[conversion.span.clone()]);
+            } else {
+                let _ = write!(out, "{conversion}");
+            }
+            last = conversion.span.end;
+
+            edits.push(Edit {
+                original: conversion.span.clone(),
+                rewritten: start..out.len(),
+            });
+        }
+        out.push_str(&text[last..]);
+
+        Self {
+            text: out,
+            edits: Some(edits),
+        }
+    }
+
+    /// Maps a byte range of the original text onto the rewritten text. A range
+    /// starting or ending inside a replaced amount is widened to cover all of
+    /// its replacement, so formatting on part of an amount applies to all of
+    /// the converted value. Returns `None` if the text was rewritten into a
+    /// list, which has no place for ranges of the original.
+    #[must_use]
+    pub fn map(&self, range: &Range<usize>) -> Option<Range<usize>> {
+        let edits = self.edits.as_deref()?;
+        let start = map_offset(edits, range.start, |edit| {
+            (range.start < edit.original.end).then_some(edit.rewritten.start)
+        });
+        let end = map_offset(edits, range.end, |edit| {
+            (range.end < edit.original.end).then_some(edit.rewritten.end)
+        });
+
+        Some(start..end.max(start))
+    }
+}
+
+/// Maps a single byte offset of the original text across `edits`, calling
+/// `inside` for the edit it falls within or at the end of.
+fn map_offset(edits: &[Edit], pos: usize, inside: impl Fn(&Edit) -> Option<usize>) -> usize {
+    let preceding = edits.partition_point(|edit| edit.original.start < pos);
+
+    match preceding.checked_sub(1).map(|idx| &edits[idx]) {
+        Some(edit) if pos <= edit.original.end => {
+            inside(edit).unwrap_or_else(|| pos - edit.original.end + edit.rewritten.end)
+        }
+        Some(edit) => pos - edit.original.end + edit.rewritten.end,
+        None => pos,
+    }
+}
+
+/// Returns `text` with every conversion's span replaced by its converted
+/// value.
+///
+/// `conversions` must be sorted and non-overlapping, as returned by
+/// [`Converter::convert_text`].
+#[must_use]
+pub fn rewrite(text: &str, conversions: &[Conversion]) -> String {
+    Rewritten::new(text, conversions, OutputStyle::Replace).text
+}
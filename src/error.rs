@@ -0,0 +1,25 @@
+use std::fmt;
+
+/// Why a single match in a message could not be converted. Other matches in
+/// the same message are unaffected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConversionError {
+    /// The digits of the amount do not form a number.
+    InvalidAmount(String),
+    /// The amount, or its converted value, is too large to be represented.
+    OutOfRange(String),
+    /// The matched alias does not belong to any unit of the catalog.
+    UnknownUnit(String),
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversionError::InvalidAmount(text) => write!(f, "invalid amount in {text:?}"),
+            ConversionError::OutOfRange(text) => write!(f, "amount out of range in {text:?}"),
+            ConversionError::UnknownUnit(text) => write!(f, "unknown unit in {text:?}"),
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {}
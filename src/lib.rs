@@ -0,0 +1,25 @@
+//! The conversion engine behind the playground metrics bot: finds amounts with
+//! units in free text and expresses them in davincis.
+
+#![deny(clippy::pedantic)]
+#![allow(clippy::similar_names)]
+
+mod catalog;
+mod converter;
+mod error;
+mod matcher;
+pub mod models;
+mod normalize;
+pub mod references;
+mod resolve;
+mod reverse;
+
+pub use catalog::{Catalog, CatalogError, InvalidEntry};
+pub use converter::{
+    format_significant, rewrite, Conversion, ConvertOptions, Converter, Locale, OutputStyle,
+    Precision, Rewritten,
+};
+pub use error::ConversionError;
+pub use models::{Metric, Position, ReferenceObject, Unit};
+pub use references::{find_reference, DAVINCI, REFERENCE_OBJECTS};
+pub use reverse::{ReverseAnswer, ReverseConversion, ReverseError};
@@ -0,0 +1,662 @@
+use std::ops::Range;
+
+use aho_corasick::{AhoCorasick, Anchored, Input, MatchKind, StartKind};
+
+use crate::{Locale, Metric, Position, Unit};
+
+/// Words that join the two halves of a compound unit, besides a slash.
+pub(crate) const PER: &str = "per";
+
+/// Suffixes multiplying the amount they follow, like the `k` of `20k €`, and
+/// whether they only do so for money, as "m" is far more often a metre.
+const MAGNITUDES: [(&str, f64, bool); 5] = [
+    ("k", 1e3, false),
+    ("K", 1e3, false),
+    ("m", 1e6, true),
+    ("M", 1e6, true),
+    ("bn", 1e9, true),
+];
+
+/// An amount and the units it may have been written with, before any
+/// conversion.
+#[derive(Debug, Clone)]
+pub(crate) struct Match<'m> {
+    pub span: Range<usize>,
+    pub amount: Result<f64, lexical_core::Error>,
+    /// Where the unit's alias was written.
+    pub alias: Range<usize>,
+    /// Every unit the alias belongs to, in catalog order. There is more than
+    /// one when the alias is ambiguous, like "pound".
+    pub candidates: &'m [Candidate],
+    /// The unit the candidate is divided by in a compound unit like `km/h`.
+    pub per: Option<usize>,
+}
+
+/// One of the units an alias may stand for.
+#[derive(Debug, Clone)]
+pub(crate) struct Candidate {
+    pub unit: usize,
+    /// The alias as spelled in the catalog.
+    pub spelling: String,
+    /// Whether the alias is one of the unit's weak aliases.
+    pub weak: bool,
+    /// Whether the alias only stands for the unit when written exactly as
+    /// `spelling`.
+    pub case_sensitive: bool,
+    pub metric: Metric,
+}
+
+impl Candidate {
+    /// Whether the alias may stand for this unit when written as `written`.
+    pub fn fits(&self, written: &str) -> bool {
+        !self.case_sensitive || written == self.spelling
+    }
+}
+
+/// The aliases that may appear on one side of an amount, compiled into a
+/// single automaton. Pattern ids index into `candidates`, which holds every
+/// unit sharing that alias.
+struct AliasSet {
+    automaton: AhoCorasick,
+    candidates: Vec<Vec<Candidate>>,
+}
+
+impl AliasSet {
+    fn new(units: &[Unit], position: Position) -> Self {
+        Self::build(
+            units,
+            units
+                .iter()
+                .enumerate()
+                .filter(|(_, unit)| unit.position.overlaps(position))
+                .flat_map(|(idx, unit)| {
+                    let weak = unit
+                        .weak_aliases
+                        .iter()
+                        .map(move |alias| (alias, idx, true, false));
+                    let case_sensitive = unit
+                        .case_sensitive_aliases
+                        .iter()
+                        .map(move |alias| (alias, idx, false, true));
+                    unit.aliases
+                        .iter()
+                        .map(move |alias| (alias, idx, false, false))
+                        .chain(weak)
+                        .chain(case_sensitive)
+                }),
+        )
+    }
+
+    /// The aliases, including compound-only ones, of every unit whose metric
+    /// passes `include`, to match one half of a compound unit.
+    fn compound(units: &[Unit], include: impl Fn(Metric) -> bool) -> Self {
+        Self::build(
+            units,
+            units
+                .iter()
+                .enumerate()
+                .filter(|(_, unit)| include(unit.metric))
+                .flat_map(|(idx, unit)| {
+                    let case_sensitive = unit
+                        .case_sensitive_aliases
+                        .iter()
+                        .chain(&unit.compound_aliases)
+                        .map(move |alias| (alias, idx, false, true));
+                    unit.aliases
+                        .iter()
+                        .map(move |alias| (alias, idx, false, false))
+                        .chain(case_sensitive)
+                }),
+        )
+    }
+
+    fn build<'a>(
+        units: &[Unit],
+        aliases: impl Iterator<Item = (&'a String, usize, bool, bool)>,
+    ) -> Self {
+        let mut patterns: Vec<&str> = Vec::new();
+        let mut candidates: Vec<Vec<Candidate>> = Vec::new();
+
+        for (alias, unit, weak, case_sensitive) in aliases {
+            let candidate = Candidate {
+                unit,
+                spelling: alias.clone(),
+                weak,
+                case_sensitive,
+                metric: units[unit].metric,
+            };
+
+            match patterns
+                .iter()
+                .position(|pattern| pattern.eq_ignore_ascii_case(alias))
+            {
+                // A unit may be listed again under the same pattern for
+                // another case-sensitive spelling, like "kB" and "KB"
+                Some(pattern)
+                    if candidates[pattern].iter().all(|other| {
+                        other.unit != unit
+                            || other.case_sensitive && case_sensitive && other.spelling != *alias
+                    }) =>
+                {
+                    candidates[pattern].push(candidate);
+                }
+                Some(_) => {}
+                None => {
+                    patterns.push(alias);
+                    candidates.push(vec![candidate]);
+                }
+            }
+        }
+
+        let automaton = AhoCorasick::builder()
+            .ascii_case_insensitive(true)
+            .match_kind(MatchKind::LeftmostLongest)
+            .start_kind(StartKind::Anchored)
+            .build(patterns)
+            .expect("unit aliases fit in an automaton");
+
+        Self {
+            automaton,
+            candidates,
+        }
+    }
+
+    /// Returns the longest alias starting exactly at `pos` that ends on a word
+    /// boundary, and the units it may stand for.
+    fn find_at(&self, text: &str, pos: usize) -> Option<(Range<usize>, &[Candidate])> {
+        let input = Input::new(text).range(pos..).anchored(Anchored::Yes);
+        let found = self.automaton.find(input)?;
+
+        if !ends_on_boundary(text, found.end()) {
+            return None;
+        }
+
+        Some((found.range(), &self.candidates[found.pattern().as_usize()]))
+    }
+}
+
+/// Finds amounts with units in a single left-to-right pass.
+///
+/// Every position of the text is visited at most once as a match start and
+/// each match consumes at least one character, so scanning always terminates
+/// and runs in time linear in the length of the text times the length of the
+/// longest alias.
+pub(crate) struct Matcher {
+    before: AliasSet,
+    after: AliasSet,
+    any: AliasSet,
+    /// The amounts and durations making up a rate such as `km/h` or `MB/s`.
+    numerators: AliasSet,
+    durations: AliasSet,
+}
+
+impl Matcher {
+    pub fn new(units: &[Unit]) -> Self {
+        Self {
+            before: AliasSet::new(units, Position::BeforeAmount),
+            after: AliasSet::new(units, Position::AfterAmount),
+            any: AliasSet::new(units, Position::Both),
+            numerators: AliasSet::compound(units, |metric| metric.per_time().is_some()),
+            durations: AliasSet::compound(units, |metric| metric == Metric::Time),
+        }
+    }
+
+    /// Returns the longest alias of any unit starting exactly at `pos`, and the
+    /// index of the first unit it belongs to in the case it was written in,
+    /// preferring units it is not a weak alias of.
+    pub fn find_alias(&self, text: &str, pos: usize) -> Option<(Range<usize>, usize)> {
+        let (alias, candidates) = self.any.find_at(text, pos)?;
+        let written = &text[alias.clone()];
+        let mut fitting = candidates
+            .iter()
+            .filter(|candidate| candidate.fits(written));
+        let first = fitting.clone().next()?;
+        let candidate = fitting.find(|candidate| !candidate.weak).unwrap_or(first);
+
+        Some((alias, candidate.unit))
+    }
+
+    /// Returns every non-overlapping match in `text`, in order, reading numbers
+    /// the way `locale` says.
+    pub fn find_all(&self, text: &str, locale: Locale) -> Vec<Match<'_>> {
+        let mut matches = Vec::new();
+        let mut pos = 0;
+
+        while let Some(c) = text[pos..].chars().next() {
+            if let Some(found) = self
+                .unit_before_amount(text, pos, locale)
+                .or_else(|| self.unit_after_amount(text, pos, locale))
+            {
+                pos = found.span.end;
+                matches.push(found);
+            } else {
+                pos = skip_amount(text, pos, locale).unwrap_or(pos + c.len_utf8());
+            }
+        }
+
+        matches
+    }
+
+    /// Matches e.g. `$5` or `€ 20k` starting at `pos`.
+    fn unit_before_amount(&self, text: &str, pos: usize, locale: Locale) -> Option<Match<'_>> {
+        let first = text[pos..].chars().next()?;
+        if is_word(first) && text[..pos].chars().next_back().is_some_and(is_word) {
+            return None;
+        }
+
+        let (alias, candidates) = self.before.find_at(text, pos)?;
+
+        let mut start = alias.end;
+        if let Some(c) = text[start..].chars().next().filter(|c| c.is_whitespace()) {
+            start += c.len_utf8();
+        }
+
+        let (mut amount, mut end) = lex_amount(text, start, locale)?;
+        if continues_number(text, end) {
+            return None;
+        }
+        let money = candidates
+            .iter()
+            .any(|candidate| candidate.metric == Metric::Currency);
+        if let Some((factor, magnitude_end, money_only)) = lex_magnitude(text, end) {
+            if money || !money_only {
+                amount = amount.map(|amount| amount * factor);
+                end = magnitude_end;
+            }
+        }
+
+        Some(Match {
+            span: pos..end,
+            amount,
+            alias,
+            candidates,
+            per: None,
+        })
+    }
+
+    /// Matches e.g. `5 kg`, `20k €`, `$5m`, `30 km/h` or `2 MB/s` starting at
+    /// `pos`.
+    fn unit_after_amount(&self, text: &str, pos: usize, locale: Locale) -> Option<Match<'_>> {
+        let (amount, end) = lex_amount(text, pos, locale)?;
+        if continues_number(text, end) {
+            return None;
+        }
+
+        if let Some((factor, magnitude_end, money_only)) = lex_magnitude(text, end) {
+            let found = self
+                .unit_after(
+                    text,
+                    pos,
+                    amount.map(|amount| amount * factor),
+                    magnitude_end,
+                )
+                .filter(|found| {
+                    !money_only
+                        || found
+                            .candidates
+                            .iter()
+                            .all(|candidate| candidate.metric == Metric::Currency)
+                });
+            if found.is_some() {
+                return found;
+            }
+        }
+
+        self.unit_after(text, pos, amount, end)
+    }
+
+    /// Matches the unit following an amount that ends at `end`.
+    fn unit_after(
+        &self,
+        text: &str,
+        pos: usize,
+        amount: Result<f64, lexical_core::Error>,
+        end: usize,
+    ) -> Option<Match<'_>> {
+        let end = skip_whitespace(text, end);
+
+        if let Some((alias, candidate, per)) = self.compound_unit(text, end) {
+            return Some(Match {
+                span: pos..alias.end,
+                amount,
+                alias,
+                candidates: std::slice::from_ref(candidate),
+                per: Some(per),
+            });
+        }
+
+        let (alias, candidates) = self.after.find_at(text, end)?;
+
+        Some(Match {
+            span: pos..alias.end,
+            amount,
+            alias,
+            candidates,
+            per: None,
+        })
+    }
+
+    /// Matches an amount divided by a duration, like `km/h` or `megabytes per
+    /// second`, starting at `pos`. Returns where the whole compound unit was
+    /// written, the numerator and the index of the duration's unit.
+    pub fn compound_unit(
+        &self,
+        text: &str,
+        pos: usize,
+    ) -> Option<(Range<usize>, &Candidate, usize)> {
+        let (numerator, candidates) = self.numerators.find_at(text, pos)?;
+
+        let start = if let Some(rest) = text[numerator.end..].strip_prefix('/') {
+            text.len() - rest.len()
+        } else {
+            let start = skip_whitespace(text, numerator.end);
+            let per = text.get(start..start + PER.len())?;
+            if start == numerator.end
+                || !per.eq_ignore_ascii_case(PER)
+                || !ends_on_boundary(text, start + PER.len())
+            {
+                return None;
+            }
+            let end = skip_whitespace(text, start + PER.len());
+            if end == start + PER.len() {
+                return None;
+            }
+            end
+        };
+
+        let numerator = candidates
+            .iter()
+            .find(|candidate| candidate.fits(&text[numerator.clone()]))?;
+        let (duration, per) = self.durations.find_at(text, start)?;
+        let per = per
+            .iter()
+            .find(|candidate| candidate.fits(&text[duration.clone()]))?;
+
+        Some((pos..duration.end, numerator, per.unit))
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+pub(crate) fn ends_on_boundary(text: &str, end: usize) -> bool {
+    let (Some(last), Some(next)) = (text[..end].chars().next_back(), text[end..].chars().next())
+    else {
+        return true;
+    };
+
+    next.is_whitespace() || is_word(last) != is_word(next)
+}
+
+pub(crate) fn skip_whitespace(text: &str, pos: usize) -> usize {
+    text[pos..]
+        .find(|c: char| !c.is_whitespace())
+        .map_or(text.len(), |offset| pos + offset)
+}
+
+/// Unicode characters for common fractions, and their values.
+const VULGAR_FRACTIONS: [(char, f64); 18] = [
+    ('½', 1.0 / 2.0),
+    ('⅓', 1.0 / 3.0),
+    ('⅔', 2.0 / 3.0),
+    ('¼', 1.0 / 4.0),
+    ('¾', 3.0 / 4.0),
+    ('⅕', 1.0 / 5.0),
+    ('⅖', 2.0 / 5.0),
+    ('⅗', 3.0 / 5.0),
+    ('⅘', 4.0 / 5.0),
+    ('⅙', 1.0 / 6.0),
+    ('⅚', 5.0 / 6.0),
+    ('⅐', 1.0 / 7.0),
+    ('⅛', 1.0 / 8.0),
+    ('⅜', 3.0 / 8.0),
+    ('⅝', 5.0 / 8.0),
+    ('⅞', 7.0 / 8.0),
+    ('⅑', 1.0 / 9.0),
+    ('⅒', 1.0 / 10.0),
+];
+
+/// Characters that only ever group digits, as in `1'234` or `1 234` with a
+/// thin space.
+const GROUP_SEPARATORS: [char; 5] = ['\'', '’', '\u{2009}', '\u{202f}', '\u{a0}'];
+
+/// Characters between the numerator and denominator of a fraction.
+const FRACTION_SLASHES: [char; 2] = ['/', '⁄'];
+
+/// Lexes an amount such as `12`, `1,234.5`, `1/2`, `½` or a mixed number
+/// like `2 1/4` or `1½` starting at `pos`, reading decimal marks and digit
+/// grouping the way `locale` says. Returns the amount's value and end. The
+/// value is an error if the digits do not parse, and infinite if they
+/// overflow or a denominator is zero.
+pub(crate) fn lex_amount(
+    text: &str,
+    pos: usize,
+    locale: Locale,
+) -> Option<(Result<f64, lexical_core::Error>, usize)> {
+    if let Some(fraction) = lex_fraction(text, pos) {
+        return Some(fraction);
+    }
+
+    let (whole, end, decimals) = lex_decimal(text, pos, locale)?;
+    if decimals {
+        return Some((whole, end));
+    }
+
+    let fraction_start = if text[end..].starts_with(' ') {
+        end + 1
+    } else {
+        end
+    };
+    // The fraction of a mixed number is proper, so "2 10/5" is not one
+    match lex_fraction(text, fraction_start) {
+        Some((Ok(fraction), fraction_end)) if (0.0..1.0).contains(&fraction) => {
+            Some((whole.map(|whole| whole + fraction), fraction_end))
+        }
+        _ => Some((whole, end)),
+    }
+}
+
+/// Where to look for the next match after an amount at `pos` that had no
+/// unit, if there is an amount at `pos`.
+///
+/// Never restarts inside the amount, or a long run of digits would be lexed
+/// once per digit, nor inside digits and separators that did not make up a
+/// number, or the list `1,2,3` would be read as `1` and `2,3` and the date
+/// `1/2/2024` as `1` and the fraction `2/2024`.
+pub(crate) fn skip_amount(text: &str, pos: usize, locale: Locale) -> Option<usize> {
+    let (_, end) = lex_amount(text, pos, locale)?;
+    let run = text[pos..]
+        .find(|c: char| !in_number(c) && !FRACTION_SLASHES.contains(&c))
+        .map_or(text.len(), |len| pos + len);
+
+    Some(end.max(run))
+}
+
+/// Whether the digits and separators before `end` go on after it, as when
+/// only the `1` of `1,2,3` is an amount.
+fn continues_number(text: &str, end: usize) -> bool {
+    let mut rest = text[end..].chars();
+    rest.next().is_some_and(in_number) && rest.next().is_some_and(|c| c.is_ascii_digit())
+}
+
+/// Whether `c` may be part of a number written with digits, like the `,` of
+/// `1,500` or the `'` of `1'500`.
+pub(crate) fn in_number(c: char) -> bool {
+    c.is_ascii_digit() || c == ',' || c == '.' || GROUP_SEPARATORS.contains(&c)
+}
+
+/// Lexes a plain number such as `12`, `3.5`, `1,500`, `1.234,5` or
+/// `1,00,000` starting at `pos`. Returns its value, its end and whether it has
+/// decimals.
+///
+/// Groups of digits must be three long, or two long but for the last one as
+/// in Indian numbers, and decimals must come last. A number that does not fit
+/// that, like the list `1,2,3`, ends before the first separator.
+fn lex_decimal(
+    text: &str,
+    pos: usize,
+    locale: Locale,
+) -> Option<(Result<f64, lexical_core::Error>, usize, bool)> {
+    let bytes = text.as_bytes();
+    let first_end = digits_end(bytes, pos);
+    if first_end == pos {
+        return None;
+    }
+
+    // Every separator followed by digits, with the range of those digits
+    let mut parts: Vec<(char, Range<usize>)> = Vec::new();
+    let mut end = first_end;
+    while let Some(separator) = text[end..].chars().next().filter(|&c| in_number(c)) {
+        let start = end + separator.len_utf8();
+        let digits = digits_end(bytes, start);
+        if digits == start {
+            break;
+        }
+        parts.push((separator, start..digits));
+        end = digits;
+    }
+
+    let mark = decimal_mark(locale, &parts);
+    let decimals = parts
+        .iter()
+        .position(|(separator, _)| Some(*separator) == mark);
+    let groups = &parts[..decimals.unwrap_or(parts.len())];
+
+    // Decimals end a number, so "1,299.99" with decimal commas is no number
+    let valid =
+        decimals.is_none_or(|idx| idx + 1 == parts.len()) && is_grouping(first_end - pos, groups);
+    let (groups, decimals) = if valid {
+        (groups, decimals.map(|idx| &parts[idx].1))
+    } else {
+        (&[][..], None)
+    };
+
+    let mut number = text[pos..first_end].to_owned();
+    for (_, digits) in groups {
+        number.push_str(&text[digits.clone()]);
+    }
+    let end = decimals.map_or_else(
+        || groups.last().map_or(first_end, |(_, digits)| digits.end),
+        |digits| digits.end,
+    );
+    if let Some(digits) = decimals {
+        number.push('.');
+        number.push_str(&text[digits.clone()]);
+    }
+
+    Some((
+        lexical_core::parse(number.as_bytes()),
+        end,
+        decimals.is_some(),
+    ))
+}
+
+/// The mark separating the decimals of a number whose separators are
+/// `parts`, if it could have any.
+fn decimal_mark(locale: Locale, parts: &[(char, Range<usize>)]) -> Option<char> {
+    match locale {
+        Locale::Point => Some('.'),
+        Locale::Comma => Some(','),
+        Locale::Auto => {
+            let count = |mark| {
+                parts
+                    .iter()
+                    .filter(|(separator, _)| *separator == mark)
+                    .count()
+            };
+            let (last, digits) = parts
+                .iter()
+                .rev()
+                .find(|(separator, _)| matches!(separator, '.' | ','))?;
+
+            // "1.234,5" and "1,234.5" end with their decimals, "1,500" is a
+            // thousand and a half, but "1,5" and "1.500" are one and a half
+            match (count('.'), count(',')) {
+                (1.., 1..) | (1, 0) => (count(*last) == 1).then_some(*last),
+                (0, 1) => (digits.len() != 3).then_some(','),
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Whether digit groups of the given lengths, after a first group
+/// `first` digits long, group thousands correctly, the Indian way included.
+/// Every group must be separated by the same character.
+fn is_grouping(first: usize, groups: &[(char, Range<usize>)]) -> bool {
+    let Some(((separator, last), middle)) = groups.split_last() else {
+        return true;
+    };
+    if last.len() != 3 || groups.iter().any(|(other, _)| other != separator) {
+        return false;
+    }
+
+    (first <= 3 && middle.iter().all(|(_, digits)| digits.len() == 3))
+        || (first <= 2 && middle.iter().all(|(_, digits)| digits.len() == 2))
+}
+
+/// Lexes a fraction such as `1/2` or `½` starting at `pos`. A slash followed
+/// by more of a number, as in a date like `1/2/2024`, is not a fraction.
+fn lex_fraction(text: &str, pos: usize) -> Option<(Result<f64, lexical_core::Error>, usize)> {
+    let first = text[pos..].chars().next()?;
+    if let Some(&(_, value)) = VULGAR_FRACTIONS.iter().find(|(c, _)| *c == first) {
+        return Some((Ok(value), pos + first.len_utf8()));
+    }
+
+    let bytes = text.as_bytes();
+    let numerator_end = digits_end(bytes, pos);
+    let slash = text[numerator_end..].chars().next()?;
+    if numerator_end == pos || !FRACTION_SLASHES.contains(&slash) {
+        return None;
+    }
+
+    let denominator_start = numerator_end + slash.len_utf8();
+    let end = digits_end(bytes, denominator_start);
+    let mut rest = text[end..].chars();
+    let continued = match rest.next() {
+        Some(c) if FRACTION_SLASHES.contains(&c) => true,
+        Some('.') => rest.next().is_some_and(|c| c.is_ascii_digit()),
+        _ => false,
+    };
+    if end == denominator_start || continued {
+        return None;
+    }
+
+    let numerator = lexical_core::parse::<f64>(&bytes[pos..numerator_end]);
+    let denominator = lexical_core::parse::<f64>(&bytes[denominator_start..end]);
+
+    Some((
+        numerator.and_then(|numerator| Ok(numerator / denominator?)),
+        end,
+    ))
+}
+
+/// The end of the run of ASCII digits starting at `pos`.
+fn digits_end(bytes: &[u8], pos: usize) -> usize {
+    bytes[pos..]
+        .iter()
+        .position(|b| !b.is_ascii_digit())
+        .map_or(bytes.len(), |len| pos + len)
+}
+
+/// Lexes a magnitude suffix like the `k` in `20k €`, which must end on a word
+/// boundary. Returns what it multiplies by, its end and whether it only
+/// applies to money.
+fn lex_magnitude(text: &str, pos: usize) -> Option<(f64, usize, bool)> {
+    MAGNITUDES
+        .iter()
+        .find(|(suffix, _, _)| {
+            text[pos..].starts_with(suffix) && ends_on_boundary(text, pos + suffix.len())
+        })
+        .map(|&(suffix, factor, money_only)| (factor, pos + suffix.len(), money_only))
+}
+
+/// Whether `alias` was written straight after the digits of its amount and
+/// could be a magnitude suffix instead, like the "m" of "5m users".
+pub(crate) fn is_magnitude_like(text: &str, alias: &Range<usize>) -> bool {
+    text[..alias.start].ends_with(|c: char| c.is_ascii_digit())
+        && MAGNITUDES
+            .iter()
+            .any(|(suffix, _, _)| text[alias.clone()] == **suffix)
+}
@@ -1,4 +1,11 @@
-#[derive(Debug)]
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::format_significant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Metric {
     Weight,
     Length,
@@ -7,49 +14,351 @@ pub enum Metric {
     Charge,
     Energy,
     Currency,
+    Temperature,
+    Time,
+    Speed,
+    Data,
+    DataRate,
+    Power,
+}
+
+impl Metric {
+    pub const ALL: [Metric; 13] = [
+        Metric::Weight,
+        Metric::Length,
+        Metric::Area,
+        Metric::Volume,
+        Metric::Charge,
+        Metric::Energy,
+        Metric::Currency,
+        Metric::Temperature,
+        Metric::Time,
+        Metric::Speed,
+        Metric::Data,
+        Metric::DataRate,
+        Metric::Power,
+    ];
+
+    /// The lowercase name used in catalogs and commands.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Metric::Weight => "weight",
+            Metric::Length => "length",
+            Metric::Area => "area",
+            Metric::Volume => "volume",
+            Metric::Charge => "charge",
+            Metric::Energy => "energy",
+            Metric::Currency => "currency",
+            Metric::Temperature => "temperature",
+            Metric::Time => "time",
+            Metric::Speed => "speed",
+            Metric::Data => "data",
+            Metric::DataRate => "datarate",
+            Metric::Power => "power",
+        }
+    }
+
+    /// The symbol of the unit amounts of this metric are converted through.
+    #[must_use]
+    pub fn base_unit(self) -> &'static str {
+        match self {
+            Metric::Weight => "kg",
+            Metric::Length => "m",
+            Metric::Area => "m²",
+            Metric::Volume => "m³",
+            Metric::Charge => "Ah",
+            Metric::Energy => "J",
+            Metric::Currency => "CNY",
+            Metric::Temperature => "K",
+            Metric::Time => "s",
+            Metric::Speed => "m/s",
+            Metric::Data => "B",
+            Metric::DataRate => "B/s",
+            Metric::Power => "W",
+        }
+    }
+
+    /// The metric of an amount of this metric per second, such as speed for
+    /// length, if it has one.
+    #[must_use]
+    pub fn per_time(self) -> Option<Metric> {
+        match self {
+            Metric::Length => Some(Metric::Speed),
+            Metric::Data => Some(Metric::DataRate),
+            Metric::Energy => Some(Metric::Power),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Metric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Metric::ALL
+            .into_iter()
+            .find(|metric| metric.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown metric `{s}`"))
+    }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
 pub enum Position {
+    #[serde(rename = "before")]
     BeforeAmount,
+    #[serde(rename = "after")]
     AfterAmount,
+    #[serde(rename = "both")]
     Both,
 }
 
-#[derive(Debug)]
+impl Position {
+    /// Whether two positions can both apply to an alias on the same side of an
+    /// amount.
+    #[must_use]
+    pub fn overlaps(self, other: Position) -> bool {
+        self == Position::Both || other == Position::Both || self == other
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Unit {
+    pub name: String,
+    pub plural: String,
     pub metric: Metric,
     pub position: Position,
+    /// How many of this unit make up one base unit of its metric.
     pub factor: f64,
+    /// Added after dividing by `factor` to reach the base unit, for scales
+    /// that do not start at zero such as degrees Celsius.
+    #[serde(default)]
+    pub offset: f64,
+    /// The spellings the unit is recognised by. Matching ignores case, but a
+    /// single letter written in another case than listed here, like the `M`
+    /// of "5M users", is taken as a sign the amount is not of this unit.
+    pub aliases: Vec<String>,
+    /// Aliases that double as ordinary words or other abbreviations, like
+    /// "in", only recognised when the words around the amount point to this
+    /// unit.
+    #[serde(default)]
+    pub weak_aliases: Vec<String>,
+    /// Symbols only recognised when written in exactly this case, because
+    /// another case means another unit, like the "mW" of milliwatts next to
+    /// the "MW" of megawatts.
+    #[serde(default)]
+    pub case_sensitive_aliases: Vec<String>,
+    /// Further aliases only recognised as part of a compound unit such as
+    /// `m/s`, where they cannot be mistaken for another unit, and only in the
+    /// case they are listed in.
+    #[serde(default)]
+    pub compound_aliases: Vec<String>,
+}
+
+/// Something familiar to express amounts in, holding one value per metric in
+/// that metric's base unit (kg, m, m², m³, Ah, J, CNY and s). Metrics the
+/// object has no sensible value for are `None`.
+///
+/// Temperatures are compared to the range the object is rated to operate in,
+/// given as its lowest and highest temperature in K. Durations are compared to
+/// how long the object lasts on a full charge, speeds to the object's length
+/// per second, data rates to filling its storage in an hour and power to its
+/// charger's rated output.
+#[derive(Debug)]
+pub struct ReferenceObject {
+    pub name: &'static str,
+    pub plural: &'static str,
+    pub weight: Option<f64>,
+    pub length: Option<f64>,
+    pub area: Option<f64>,
+    pub volume: Option<f64>,
+    pub charge: Option<f64>,
+    pub energy: Option<f64>,
+    pub currency: Option<f64>,
+    pub temperature: Option<(f64, f64)>,
+    pub time: Option<f64>,
+    pub storage: Option<f64>,
+    pub power: Option<f64>,
 }
 
-// 191g
-const DAVINCI_KG: f64 = 0.191;
-// 156.7mm
-const DAVINCI_M: f64 = 0.1567;
-// 156.7mm * 74.3mm
-const DAVINCI_SQM: f64 = 0.011_642_81;
-// 156.7mm * 74.3mm * 8.8mm
-const DAVINCI_CM: f64 = 0.000_102_456_7;
-// 400mAh
-const DAVINCI_AH: f64 = 4.0;
-// 15.4Wh
-const DAVINCI_J: f64 = 55440.0;
-// 1999CNY on release
-const DAVINCI_CNY: f64 = 1999.0;
+impl ReferenceObject {
+    #[must_use]
+    pub fn value(&self, metric: Metric) -> Option<f64> {
+        match metric {
+            Metric::Weight => self.weight,
+            Metric::Length | Metric::Speed => self.length,
+            Metric::Area => self.area,
+            Metric::Volume => self.volume,
+            Metric::Charge => self.charge,
+            Metric::Energy => self.energy,
+            Metric::Currency => self.currency,
+            Metric::Temperature => self.temperature.map(|(low, high)| high - low),
+            Metric::Time => self.time,
+            Metric::Data => self.storage,
+            Metric::DataRate => self.storage.map(|storage| storage / 3600.0),
+            Metric::Power => self.power,
+        }
+    }
+
+    /// Formats an amount of this object the way the bot prints it, e.g.
+    /// `"26.18 davincis"`.
+    #[must_use]
+    pub fn format(&self, amount: f64) -> String {
+        format_counted(amount, self.name, self.plural)
+    }
+
+    /// Formats a duration expressed in full charges of this object, e.g.
+    /// `"2.4 davinci battery lives"`.
+    #[must_use]
+    pub fn format_time(&self, amount: f64) -> String {
+        format_counted(
+            amount,
+            &format!("{} battery life", self.name),
+            &format!("{} battery lives", self.name),
+        )
+    }
+
+    /// Formats a speed expressed in lengths of this object per second, e.g.
+    /// `"3.2 davincis per second"`.
+    #[must_use]
+    pub fn format_speed(&self, amount: f64) -> String {
+        format_counted(
+            amount,
+            &format!("{} per second", self.name),
+            &format!("{} per second", self.plural),
+        )
+    }
+
+    /// Formats an amount of data expressed in storage capacities of this
+    /// object, e.g. `"2 davinci storages"`.
+    #[must_use]
+    pub fn format_storage(&self, amount: f64) -> String {
+        format_counted(
+            amount,
+            &format!("{} storage", self.name),
+            &format!("{} storages", self.name),
+        )
+    }
+
+    /// Formats a data rate expressed in storage capacities of this object per
+    /// hour, e.g. `"28.13 davinci storages per hour"`.
+    #[must_use]
+    pub fn format_storage_rate(&self, amount: f64) -> String {
+        format_counted(
+            amount,
+            &format!("{} storage per hour", self.name),
+            &format!("{} storages per hour", self.name),
+        )
+    }
+
+    /// Formats power expressed in outputs of this object's charger, e.g.
+    /// `"2.5 davinci chargers"`.
+    #[must_use]
+    pub fn format_power(&self, amount: f64) -> String {
+        format_counted(
+            amount,
+            &format!("{} charger", self.name),
+            &format!("{} chargers", self.name),
+        )
+    }
+
+    /// Formats a temperature expressed relative to this object's operating
+    /// range, as returned by [`Unit::in_reference`]. Absolute temperatures are
+    /// placed within or beyond the range, differences are given in multiples
+    /// of its width.
+    #[must_use]
+    pub fn format_temperature(&self, value: f64, difference: bool) -> String {
+        let ranges = |amount: f64| {
+            format_counted(
+                amount,
+                &format!("{} operating range", self.name),
+                &format!("{} operating ranges", self.name),
+            )
+        };
+
+        if difference {
+            ranges(value)
+        } else if value < 0.0 {
+            format!("{} too cold", ranges(-value))
+        } else if value > 1.0 {
+            format!("{} too hot", ranges(value - 1.0))
+        } else {
+            format!(
+                "{}% into the {}'s operating range",
+                (value * 100.0).round(),
+                self.name
+            )
+        }
+    }
+}
+
+/// Formats `amount` like [`format_count`] and follows it with `singular` or
+/// `plural`.
+fn format_counted(amount: f64, singular: &str, plural: &str) -> String {
+    if (round_count(amount) - 1.0).abs() < f64::EPSILON {
+        format!("{} {singular}", format_count(amount))
+    } else {
+        format!("{} {plural}", format_count(amount))
+    }
+}
+
+/// Formats an amount of objects with the two decimals it is usually shown
+/// with, or with its first significant digits where those would round a
+/// non-zero amount to 0.
+pub(crate) fn format_count(amount: f64) -> String {
+    let rounded = round_count(amount);
+
+    if rounded == 0.0 && amount != 0.0 {
+        format_significant(amount)
+    } else {
+        rounded.to_string()
+    }
+}
+
+/// Rounds an amount of objects to the two decimals it is shown with.
+fn round_count(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
 
 impl Unit {
-    pub fn in_davincis(&self, amount: f64) -> f64 {
-        let amount = amount / self.factor;
-
-        match self.metric {
-            Metric::Weight => amount / DAVINCI_KG,
-            Metric::Length => amount / DAVINCI_M,
-            Metric::Area => amount / DAVINCI_SQM,
-            Metric::Volume => amount / DAVINCI_CM,
-            Metric::Charge => amount / DAVINCI_AH,
-            Metric::Energy => amount / DAVINCI_J,
-            Metric::Currency => amount / DAVINCI_CNY,
+    /// Expresses `amount` of this unit in multiples of `reference`, or `None`
+    /// if the reference has no value for this unit's metric.
+    ///
+    /// An absolute temperature becomes its position in the reference's
+    /// operating range, 0 being the lowest and 1 the highest rated temperature.
+    #[must_use]
+    pub fn in_reference(&self, amount: f64, reference: &ReferenceObject) -> Option<f64> {
+        let base = amount / self.factor + self.offset;
+
+        match (self.metric, reference.temperature) {
+            (Metric::Temperature, Some((low, high))) => Some((base - low) / (high - low)),
+            _ => Some(base / reference.value(self.metric)?),
         }
     }
+
+    /// Like [`Unit::in_reference`], but for a difference between two amounts,
+    /// such as a temperature rising by 5 °C, which ignores `offset`.
+    #[must_use]
+    pub fn difference_in_reference(&self, amount: f64, reference: &ReferenceObject) -> Option<f64> {
+        Some(amount / self.factor / reference.value(self.metric)?)
+    }
+
+    /// The inverse of [`Unit::in_reference`]: expresses `value` reference
+    /// objects in this unit.
+    #[must_use]
+    pub fn from_reference(&self, value: f64, reference: &ReferenceObject) -> Option<f64> {
+        let base = match (self.metric, reference.temperature) {
+            (Metric::Temperature, Some((low, high))) => low + value * (high - low),
+            _ => value * reference.value(self.metric)?,
+        };
+
+        Some((base - self.offset) * self.factor)
+    }
 }
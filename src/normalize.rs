@@ -0,0 +1,140 @@
+use std::ops::Range;
+
+use text2num::{find_numbers, Language, Token};
+
+/// A piece of text with number words such as "twenty five" replaced by
+/// digits, remembering where every replacement came from so that spans found
+/// in the normalized text can be mapped back onto the original.
+pub(crate) struct Normalized {
+    pub text: String,
+    edits: Vec<Edit>,
+}
+
+/// One number written out in words, replaced by its digits.
+struct Edit {
+    original: Range<usize>,
+    normalized: Range<usize>,
+}
+
+/// A word or separator of the original text, borrowed with its byte offset.
+struct Word<'a> {
+    text: &'a str,
+    start: usize,
+}
+
+impl Token for &Word<'_> {
+    fn text(&self) -> &str {
+        self.text
+    }
+
+    fn text_lowercase(&self) -> String {
+        self.text.to_lowercase()
+    }
+
+    fn nt_separated(&self, _previous: &Self) -> bool {
+        false
+    }
+}
+
+impl Normalized {
+    pub fn new(input: &str) -> Self {
+        let english = Language::english();
+        let words = tokenize(input);
+
+        let mut text = String::with_capacity(input.len());
+        let mut edits = Vec::new();
+        let mut last = 0;
+
+        // Ordinals are never amounts, and "second" is far more often a unit
+        for number in find_numbers(words.iter(), &english, 0.0)
+            .into_iter()
+            .filter(|number| !number.is_ordinal)
+        {
+            let original = words[number.start].start
+                ..words.get(number.end).map_or(input.len(), |word| word.start);
+
+            text.push_str(&input[last..original.start]);
+            let start = text.len();
+            text.push_str(&number.text);
+            last = original.end;
+
+            edits.push(Edit {
+                original,
+                normalized: start..text.len(),
+            });
+        }
+        text.push_str(&input[last..]);
+
+        Self { text, edits }
+    }
+
+    /// Maps a byte range of the normalized text onto the original text. A range
+    /// touching part of a replaced number is widened to the whole number words.
+    pub fn to_original(&self, span: &Range<usize>) -> Range<usize> {
+        let start = self.map(span.start, |edit| edit.original.start);
+        let end = if span.end > span.start {
+            self.map(span.end - 1, |edit| edit.original.end - 1) + 1
+        } else {
+            start
+        };
+
+        start..end
+    }
+
+    /// Whether any part of `span` of the normalized text was written out in
+    /// words in the original.
+    pub fn is_written_out(&self, span: &Range<usize>) -> bool {
+        let preceding = self
+            .edits
+            .partition_point(|edit| edit.normalized.start < span.end);
+
+        preceding
+            .checked_sub(1)
+            .is_some_and(|idx| self.edits[idx].normalized.end > span.start)
+    }
+
+    /// Maps a single byte offset, calling `inside` when it falls within a
+    /// replaced number.
+    fn map(&self, pos: usize, inside: impl Fn(&Edit) -> usize) -> usize {
+        let preceding = self
+            .edits
+            .partition_point(|edit| edit.normalized.start <= pos);
+
+        match preceding.checked_sub(1).map(|idx| &self.edits[idx]) {
+            Some(edit) if pos < edit.normalized.end => inside(edit),
+            Some(edit) => pos - edit.normalized.end + edit.original.end,
+            None => pos,
+        }
+    }
+}
+
+/// Splits text into words and separators the same way `text2num` does
+/// internally, but keeping track of byte offsets.
+fn tokenize(input: &str) -> Vec<Word<'_>> {
+    let mut words = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let in_token: fn(char) -> bool = if c.is_alphanumeric() {
+            |c| c.is_alphanumeric() || c == '-' || c == '\''
+        } else {
+            |c| !c.is_alphanumeric()
+        };
+
+        let mut end = input.len();
+        while let Some(&(pos, c)) = chars.peek() {
+            if !in_token(c) {
+                end = pos;
+                break;
+            }
+            chars.next();
+        }
+
+        words.push(Word {
+            text: &input[start..end],
+            start,
+        });
+    }
+
+    words
+}
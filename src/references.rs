@@ -0,0 +1,182 @@
+use crate::ReferenceObject;
+
+// 191g
+pub const DAVINCI_KG: f64 = 0.191;
+// 156.7mm
+pub const DAVINCI_M: f64 = 0.1567;
+// 156.7mm * 74.3mm
+pub const DAVINCI_SQM: f64 = 0.011_642_81;
+// 156.7mm * 74.3mm * 8.8mm
+pub const DAVINCI_CM: f64 = 0.000_102_456_7;
+// 4000mAh
+pub const DAVINCI_AH: f64 = 4.0;
+// 15.4Wh
+pub const DAVINCI_J: f64 = 55440.0;
+// 1999CNY on release
+pub const DAVINCI_CNY: f64 = 1999.0;
+// Rated to operate between 0°C and 35°C
+pub const DAVINCI_K: (f64, f64) = (273.15, 308.15);
+// About a day and a half of typical use on a full charge
+pub const DAVINCI_S: f64 = 129_600.0;
+// 64GB on the base model
+pub const DAVINCI_B: f64 = 64e9;
+// 18W charger in the box
+pub const DAVINCI_W: f64 = 18.0;
+
+/// The Redmi K20, the reference the bot was built around.
+pub static DAVINCI: ReferenceObject = ReferenceObject {
+    name: "davinci",
+    plural: "davincis",
+    weight: Some(DAVINCI_KG),
+    length: Some(DAVINCI_M),
+    area: Some(DAVINCI_SQM),
+    volume: Some(DAVINCI_CM),
+    charge: Some(DAVINCI_AH),
+    energy: Some(DAVINCI_J),
+    currency: Some(DAVINCI_CNY),
+    temperature: Some(DAVINCI_K),
+    time: Some(DAVINCI_S),
+    storage: Some(DAVINCI_B),
+    power: Some(DAVINCI_W),
+};
+
+/// The Xiaomi Mi 9.
+pub static CEPHEUS: ReferenceObject = ReferenceObject {
+    name: "cepheus",
+    plural: "cepheuses",
+    // 173g
+    weight: Some(0.173),
+    // 157.5mm
+    length: Some(0.1575),
+    // 157.5mm * 74.7mm
+    area: Some(0.011_765_25),
+    // 157.5mm * 74.7mm * 7.6mm
+    volume: Some(0.000_089_415_9),
+    // 3300mAh
+    charge: Some(3.3),
+    // 12.7Wh
+    energy: Some(45738.0),
+    // 2999CNY on release
+    currency: Some(2999.0),
+    // 0°C to 35°C
+    temperature: Some((273.15, 308.15)),
+    // About a day of typical use
+    time: Some(86_400.0),
+    // 64GB on the base model
+    storage: Some(64e9),
+    // 27W charger in the box
+    power: Some(27.0),
+};
+
+/// The Redmi Note 7.
+pub static LAVENDER: ReferenceObject = ReferenceObject {
+    name: "lavender",
+    plural: "lavenders",
+    // 186g
+    weight: Some(0.186),
+    // 159.2mm
+    length: Some(0.1592),
+    // 159.2mm * 75.2mm
+    area: Some(0.011_971_84),
+    // 159.2mm * 75.2mm * 8.1mm
+    volume: Some(0.000_096_971_9),
+    // 4000mAh
+    charge: Some(4.0),
+    // 15.4Wh
+    energy: Some(55440.0),
+    // 999CNY on release
+    currency: Some(999.0),
+    // 0°C to 35°C
+    temperature: Some((273.15, 308.15)),
+    // About a day and a half of typical use
+    time: Some(129_600.0),
+    // 32GB on the base model
+    storage: Some(32e9),
+    // 10W charger in the box
+    power: Some(10.0),
+};
+
+/// The Xiaomi Mi 6.
+pub static SAGIT: ReferenceObject = ReferenceObject {
+    name: "sagit",
+    plural: "sagits",
+    // 168g
+    weight: Some(0.168),
+    // 145.2mm
+    length: Some(0.1452),
+    // 145.2mm * 70.5mm
+    area: Some(0.010_236_6),
+    // 145.2mm * 70.5mm * 7.5mm
+    volume: Some(0.000_076_774_5),
+    // 3350mAh
+    charge: Some(3.35),
+    // 12.9Wh
+    energy: Some(46431.0),
+    // 2499CNY on release
+    currency: Some(2499.0),
+    // 0°C to 35°C
+    temperature: Some((273.15, 308.15)),
+    // About a day of typical use
+    time: Some(86_400.0),
+    // 64GB on the base model
+    storage: Some(64e9),
+    // 18W charger in the box
+    power: Some(18.0),
+};
+
+/// An ISO/IEC 7810 ID-1 card.
+pub static CREDIT_CARD: ReferenceObject = ReferenceObject {
+    name: "credit card",
+    plural: "credit cards",
+    // 5g
+    weight: Some(0.005),
+    // 85.6mm
+    length: Some(0.0856),
+    // 85.6mm * 53.98mm
+    area: Some(0.004_620_688),
+    // 85.6mm * 53.98mm * 0.76mm
+    volume: Some(0.000_003_511_7),
+    charge: None,
+    energy: None,
+    currency: None,
+    temperature: None,
+    time: None,
+    storage: None,
+    power: None,
+};
+
+/// A medium banana, for scale.
+pub static BANANA: ReferenceObject = ReferenceObject {
+    name: "banana",
+    plural: "bananas",
+    // 118g
+    weight: Some(0.118),
+    // 178mm
+    length: Some(0.178),
+    area: None,
+    // about 120ml
+    volume: Some(0.000_12),
+    charge: None,
+    // 105kcal
+    energy: Some(439_320.0),
+    // 0.5CNY
+    currency: Some(0.5),
+    // Ripens best between 13°C and 18°C
+    temperature: Some((286.15, 291.15)),
+    time: None,
+    storage: None,
+    power: None,
+};
+
+/// Every built-in reference object, davinci first.
+pub static REFERENCE_OBJECTS: [&ReferenceObject; 6] =
+    [&DAVINCI, &CEPHEUS, &LAVENDER, &SAGIT, &CREDIT_CARD, &BANANA];
+
+/// Looks up a built-in reference object by its singular or plural name,
+/// ignoring case.
+#[must_use]
+pub fn find_reference(name: &str) -> Option<&'static ReferenceObject> {
+    REFERENCE_OBJECTS.into_iter().find(|reference| {
+        reference.name.eq_ignore_ascii_case(name) || reference.plural.eq_ignore_ascii_case(name)
+    })
+}
@@ -0,0 +1,73 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, PoisonError, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use playground_metrics::{Catalog, Converter};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    time,
+};
+
+/// How often the catalog file is checked for modifications.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The converter currently in use. Reloading swaps in a whole new converter,
+/// so a message that already took a snapshot finishes against the old one.
+pub type SharedConverter = Arc<RwLock<Arc<Converter>>>;
+
+/// Returns the converter to handle the next message with.
+pub fn current(converter: &SharedConverter) -> Arc<Converter> {
+    Arc::clone(&converter.read().unwrap_or_else(PoisonError::into_inner))
+}
+
+/// Reloads the catalog at `path` whenever the process receives SIGHUP or the
+/// file's modification time changes. A catalog that fails to load is logged
+/// and ignored, leaving the previous one active.
+pub async fn watch(path: PathBuf, converter: SharedConverter) {
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => Some(hangup),
+        Err(err) => {
+            eprintln!("Could not listen for SIGHUP, only watching the catalog file: {err}");
+            None
+        }
+    };
+    let mut poll = time::interval(POLL_INTERVAL);
+    let mut last_modified = modified(&path);
+
+    loop {
+        tokio::select! {
+            Some(()) = async { hangup.as_mut()?.recv().await } => {
+                println!("Received SIGHUP");
+            }
+            _ = poll.tick() => {
+                let modified = modified(&path);
+                if modified == last_modified {
+                    continue;
+                }
+                println!("Unit catalog changed on disk");
+            }
+        }
+
+        last_modified = modified(&path);
+        reload(&path, &converter);
+    }
+}
+
+fn reload(path: &Path, converter: &SharedConverter) {
+    match Catalog::from_path(path) {
+        Ok(catalog) => {
+            let units = catalog.units().len();
+            let reloaded = Arc::new(Converter::new(catalog));
+            *converter.write().unwrap_or_else(PoisonError::into_inner) = reloaded;
+            println!("Reloaded unit catalog with {units} units");
+        }
+        Err(err) => eprintln!("Keeping the previous unit catalog: {err}"),
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
@@ -0,0 +1,212 @@
+use std::ops::Range;
+
+use crate::{
+    matcher::{is_magnitude_like, Match},
+    Metric, Unit,
+};
+
+/// How many words on either side of an amount are looked at for cues.
+const CONTEXT_WORDS: usize = 6;
+
+/// The score an interpretation starts from when matched by a regular alias.
+const STRONG_ALIAS: f64 = 1.0;
+/// The score an interpretation starts from when matched by a weak alias, like
+/// the "in" of inches. Too low to be kept without further evidence.
+const WEAK_ALIAS: f64 = 0.4;
+/// Added for every cue word for the unit's metric near the amount.
+const CUE: f64 = 0.75;
+/// Added for the metric a chat prefers most, and a fraction of it for the
+/// ones it prefers less.
+const PREFERRED: f64 = 0.3;
+/// Added when a weak alias with capitals is written exactly as in the
+/// catalog, like "Rs".
+const EXACT_CASE: f64 = 0.3;
+/// Subtracted when a single-letter alias is written in the other case than
+/// in the catalog, like the "M" of "5M users".
+const MISCASED: f64 = -0.6;
+/// Subtracted when a weak alias is directly followed by another number, like
+/// the "in" of "1 in 10". Regular aliases are often followed by the next
+/// part of a mixed quantity, as in "1 kg 200 g".
+const NUMBER_AFTER: f64 = -0.6;
+/// Subtracted when an alias that could also be a magnitude suffix, like the
+/// "m" of "5m users", is written straight after the digits and the amount
+/// reads as a count or as money: followed by a plural noun or near a word
+/// about money, with no cue for the unit's metric. Enough to outweigh even
+/// the chat's preferred metric.
+const MAGNITUDE_LIKE: f64 = -0.9;
+/// Subtracted when the amount is written in words, which happens far more
+/// often in phrases like "one in a million" than in measurements.
+const WRITTEN_OUT: f64 = -0.2;
+/// Plural nouns not ending in "s" that often follow a count, as in "5m
+/// people".
+const IRREGULAR_PLURALS: [&str; 4] = ["people", "children", "men", "women"];
+/// Interpretations scoring below this are not converted at all.
+const THRESHOLD: f64 = 0.5;
+
+/// Words suggesting that an amount near them is of a metric, used to tell
+/// apart units sharing an alias and to confirm weak aliases.
+const CUES: [(Metric, &[&str]); 5] = [
+    (
+        Metric::Weight,
+        &[
+            "weigh", "weighs", "weighed", "weight", "heavy", "heavier", "light", "lighter", "lost",
+            "lose", "gain", "gained", "lift", "lifted", "bench", "diet", "fat", "muscle", "scale",
+            "kg", "kgs", "oz", "ounces", "stone", "lb", "lbs",
+        ],
+    ),
+    (
+        Metric::Currency,
+        &[
+            "£", "cost", "costs", "paid", "pay", "price", "priced", "pricey", "spend", "spent",
+            "worth", "buy", "bought", "sell", "sold", "salary", "earn", "earned", "owe", "cheap",
+            "money", "bank", "fee", "rent", "bill", "pence", "quid", "sterling", "rupees",
+            "forints",
+        ],
+    ),
+    (
+        Metric::Length,
+        &[
+            "long", "longer", "tall", "taller", "wide", "wider", "high", "deep", "length", "width",
+            "height", "depth", "away", "far", "distance", "walk", "walked", "ran", "screen",
+            "display", "inch", "inches", "feet", "foot", "ft", "cm", "metres", "meters",
+        ],
+    ),
+    (
+        Metric::Time,
+        &[
+            "wait", "waited", "waiting", "ago", "later", "took", "takes", "lasted", "lasts",
+            "late", "delay", "timer", "left", "minutes", "min", "hours", "seconds",
+        ],
+    ),
+    (
+        Metric::Volume,
+        &[
+            "water", "milk", "fuel", "gas", "petrol", "diesel", "tank", "drink", "drank", "bottle",
+            "jug", "bucket", "liquid", "litres", "liters",
+        ],
+    ),
+];
+
+/// Decides which unit, if any, the amount `found` was written with. Every
+/// candidate unit of the alias is scored on the words around the amount, how
+/// the alias was written and what the chat `prefer`s; the best one is kept,
+/// the earliest in the catalog on a tie, unless it scores below
+/// [`THRESHOLD`].
+///
+/// `written_out` tells whether the amount was written in words.
+pub(crate) fn resolve(
+    text: &str,
+    found: &Match,
+    written_out: bool,
+    units: &[Unit],
+    prefer: &[Metric],
+) -> Option<usize> {
+    let context: Vec<&str> = words(&text[..found.span.start])
+        .rev()
+        .take(CONTEXT_WORDS)
+        .chain(words(&text[found.span.end..]).take(CONTEXT_WORDS))
+        .collect();
+    let written = &text[found.alias.clone()];
+    let number_after = found.alias.end == found.span.end && number_follows(text, &found.alias);
+    let magnitude_like = found.per.is_none()
+        && is_magnitude_like(text, &found.alias)
+        && (counts_after(text, found) || cue_count(Metric::Currency, &context) > 0);
+
+    let mut best: Option<(f64, usize)> = None;
+
+    for candidate in found.candidates {
+        // The halves of a compound unit were already checked by the matcher
+        if found.per.is_none() && !candidate.fits(written) {
+            continue;
+        }
+        let Some(metric) = units.get(candidate.unit).map(|unit| unit.metric) else {
+            // Unknown units are reported by the converter
+            return Some(candidate.unit);
+        };
+
+        let mut score = if candidate.weak {
+            WEAK_ALIAS
+        } else {
+            STRONG_ALIAS
+        };
+        let cues = cue_count(metric, &context);
+        #[allow(clippy::cast_precision_loss)]
+        {
+            score += CUE * cues as f64;
+            if let Some(rank) = prefer.iter().position(|&preferred| preferred == metric) {
+                score += PREFERRED / (rank + 1) as f64;
+            }
+        }
+        if candidate.weak
+            && written == candidate.spelling
+            && candidate.spelling.chars().any(char::is_uppercase)
+        {
+            score += EXACT_CASE;
+        }
+        if is_miscased(written, &candidate.spelling) {
+            score += MISCASED;
+        }
+        if candidate.weak && number_after {
+            score += NUMBER_AFTER;
+        }
+        if magnitude_like && cues == 0 {
+            score += MAGNITUDE_LIKE;
+        }
+        if written_out {
+            score += WRITTEN_OUT;
+        }
+
+        if best.is_none_or(|(best, _)| score > best) {
+            best = Some((score, candidate.unit));
+        }
+    }
+
+    best.filter(|&(score, _)| score >= THRESHOLD)
+        .map(|(_, unit)| unit)
+}
+
+/// How many of the words in `context` are cues for `metric`.
+fn cue_count(metric: Metric, context: &[&str]) -> usize {
+    CUES.iter()
+        .filter(|(cued, _)| *cued == metric)
+        .flat_map(|(_, cues)| cues.iter())
+        .map(|cue| {
+            context
+                .iter()
+                .filter(|word| word.eq_ignore_ascii_case(cue))
+                .count()
+        })
+        .sum()
+}
+
+/// Whether a single-letter alias was written in the other case than its
+/// catalog spelling.
+fn is_miscased(written: &str, spelling: &str) -> bool {
+    let mut letters = spelling.chars();
+    matches!((letters.next(), letters.next()), (Some(letter), None) if letter.is_alphabetic())
+        && written != spelling
+}
+
+/// Whether the amount is followed by a plural noun, as in "5m users", which
+/// a count or a magnitude can be but a measurement rarely is.
+fn counts_after(text: &str, found: &Match) -> bool {
+    words(&text[found.span.end..]).next().is_some_and(|word| {
+        let word = word.to_lowercase();
+        IRREGULAR_PLURALS.contains(&word.as_str())
+            || word.len() > 3 && word.ends_with('s') && !word.ends_with("ss")
+    })
+}
+
+/// Whether a number directly follows `alias`, only separated by whitespace.
+fn number_follows(text: &str, alias: &Range<usize>) -> bool {
+    text[alias.end..]
+        .trim_start()
+        .starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Splits `text` into words and symbols like `£`, dropping whitespace, digits
+/// and punctuation.
+fn words(text: &str) -> impl DoubleEndedIterator<Item = &str> {
+    text.split(|c: char| c.is_whitespace() || c.is_numeric() || c.is_ascii_punctuation())
+        .filter(|word| !word.is_empty())
+}
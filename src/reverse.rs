@@ -0,0 +1,222 @@
+use std::{fmt, ops::Range};
+
+use crate::{
+    format_significant,
+    matcher::{ends_on_boundary, lex_amount, skip_amount, skip_whitespace, Matcher, PER},
+    normalize::Normalized,
+    ConvertOptions, Locale, ReferenceObject, Unit, REFERENCE_OBJECTS,
+};
+
+/// Words that separate the reference object from the target unit, as in
+/// "12 davincis in kg".
+const SEPARATORS: [&str; 3] = ["into", "in", "to"];
+
+/// An amount of reference objects converted back into a real unit.
+#[derive(Debug, Clone)]
+pub struct ReverseConversion<'a> {
+    /// Byte range of the whole query, e.g. `12 davincis in kg`, in the
+    /// original text.
+    pub span: Range<usize>,
+    /// How many reference objects were asked about.
+    pub value: f64,
+    pub reference: &'static ReferenceObject,
+    /// The unit the answer is expressed in.
+    pub unit: &'a Unit,
+    /// The unit `unit` is divided by when the answer is a rate like `km/h`.
+    pub per: Option<&'a Unit>,
+    /// The answer, expressed in `unit`.
+    pub amount: f64,
+}
+
+/// Why a query like "12 davincis in kg" could not be answered.
+#[derive(Debug, Clone)]
+pub enum ReverseError<'a> {
+    /// The target is not an alias of any unit in the catalog.
+    UnknownUnit(String),
+    /// The reference object has no value for the target unit's metric, like
+    /// the charge of a banana.
+    UnsupportedMetric {
+        reference: &'static ReferenceObject,
+        unit: &'a Unit,
+    },
+}
+
+/// The answer to a single query, or why it could not be answered.
+pub type ReverseAnswer<'a> = Result<ReverseConversion<'a>, ReverseError<'a>>;
+
+/// Finds every query of the form `<amount> <reference> in|into|to <unit>` in
+/// `text`, outside the ranges `options` skips, and answers it.
+pub(crate) fn convert_reverse<'a>(
+    matcher: &Matcher,
+    units: &'a [Unit],
+    text: &str,
+    options: &ConvertOptions,
+) -> Vec<ReverseAnswer<'a>> {
+    let normalized = Normalized::new(text);
+    let text = normalized.text.as_str();
+
+    let mut results = Vec::new();
+    let mut pos = 0;
+
+    while let Some(c) = text[pos..].chars().next() {
+        if let Some((span, result)) = query_at(matcher, units, text, pos, options.locale) {
+            pos = span.end;
+            let span = normalized.to_original(&span);
+            if options.skips(&span) {
+                continue;
+            }
+            results.push(result.map(|conversion| ReverseConversion { span, ..conversion }));
+        } else {
+            pos = skip_amount(text, pos, options.locale).unwrap_or(pos + c.len_utf8());
+        }
+    }
+
+    results
+}
+
+/// Parses a query starting at `pos`, reading its amount the way `locale` says,
+/// and returns its span in `text` along with the answer.
+fn query_at<'a>(
+    matcher: &Matcher,
+    units: &'a [Unit],
+    text: &str,
+    pos: usize,
+    locale: Locale,
+) -> Option<(Range<usize>, ReverseAnswer<'a>)> {
+    let (value, end) = lex_amount(text, pos, locale)?;
+    let value = value.ok().filter(|value| value.is_finite())?;
+
+    let (reference, end) = find_reference_at(text, skip_whitespace(text, end))?;
+    let end = find_separator_at(text, skip_whitespace(text, end))?;
+    let start = skip_whitespace(text, end);
+    if start == end {
+        return None;
+    }
+
+    let unknown = |end: usize| {
+        let word = text[start..]
+            .split(|c: char| c.is_whitespace() || matches!(c, ',' | '.' | '?' | '!'))
+            .next()
+            .filter(|word| !word.is_empty())?;
+        let end = end.max(start + word.len());
+
+        Some((
+            pos..end,
+            Err(ReverseError::UnknownUnit(text[start..end].to_owned())),
+        ))
+    };
+
+    let (alias, unit, per) =
+        if let Some((alias, numerator, per)) = matcher.compound_unit(text, start) {
+            (alias, numerator.unit, Some(&units[per]))
+        } else {
+            let Some((alias, unit)) = matcher.find_alias(text, start) else {
+                return unknown(start);
+            };
+            // A rate whose duration is not known, like "km per fortnight", must
+            // not be answered in its length alone
+            if let Some(end) = per_word_end(text, alias.end) {
+                return unknown(end);
+            }
+            (alias, unit, None)
+        };
+
+    let unit = &units[unit];
+    let amount = match per {
+        Some(per) => unit
+            .metric
+            .per_time()
+            .and_then(|metric| Some(value * reference.value(metric)? * unit.factor / per.factor)),
+        None => unit.from_reference(value, reference),
+    };
+    let result = amount
+        .map(|amount| ReverseConversion {
+            span: pos..alias.end,
+            value,
+            reference,
+            unit,
+            per,
+            amount,
+        })
+        .ok_or(ReverseError::UnsupportedMetric { reference, unit });
+
+    Some((pos..alias.end, result))
+}
+
+/// If a slash or "per" follows a unit ending at `pos`, the end of the word
+/// after it.
+fn per_word_end(text: &str, pos: usize) -> Option<usize> {
+    let start = if text[pos..].starts_with('/') {
+        pos + 1
+    } else {
+        let start = skip_whitespace(text, pos);
+        if start == pos || !starts_with_word(text, start, PER) {
+            return None;
+        }
+        skip_whitespace(text, start + PER.len())
+    };
+    let len = text[start..]
+        .find(|c: char| !c.is_alphanumeric())
+        .unwrap_or(text.len() - start);
+
+    Some(start + len)
+}
+
+/// Matches the longest singular or plural name of a built-in reference object
+/// at `pos`, returning the object and the end of its name.
+fn find_reference_at(text: &str, pos: usize) -> Option<(&'static ReferenceObject, usize)> {
+    REFERENCE_OBJECTS
+        .into_iter()
+        .flat_map(|reference| [(reference, reference.name), (reference, reference.plural)])
+        .filter(|(_, name)| starts_with_word(text, pos, name))
+        .max_by_key(|(_, name)| name.len())
+        .map(|(reference, name)| (reference, pos + name.len()))
+}
+
+fn find_separator_at(text: &str, pos: usize) -> Option<usize> {
+    SEPARATORS
+        .into_iter()
+        .find(|separator| starts_with_word(text, pos, separator))
+        .map(|separator| pos + separator.len())
+}
+
+/// Whether `word` appears at `pos`, ignoring ASCII case, and ends on a word
+/// boundary.
+fn starts_with_word(text: &str, pos: usize, word: &str) -> bool {
+    text.get(pos..pos + word.len())
+        .is_some_and(|found| found.eq_ignore_ascii_case(word))
+        && ends_on_boundary(text, pos + word.len())
+}
+
+impl fmt::Display for ReverseConversion<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut unit = if (self.amount - 1.0).abs() < f64::EPSILON {
+            self.unit.name.clone()
+        } else {
+            self.unit.plural.clone()
+        };
+        if let Some(per) = self.per {
+            unit = format!("{unit} per {}", per.name);
+        }
+
+        write!(
+            f,
+            "{} is {} {unit}",
+            self.reference.format(self.value),
+            format_significant(self.amount)
+        )
+    }
+}
+
+impl fmt::Display for ReverseError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReverseError::UnknownUnit(unit) => write!(f, "I don't know the unit \"{unit}\"."),
+            ReverseError::UnsupportedMetric { reference, unit } => write!(
+                f,
+                "{} cannot be converted into {}, a {} has no {} to compare with.",
+                reference.plural, unit.plural, reference.name, unit.metric
+            ),
+        }
+    }
+}
@@ -0,0 +1,373 @@
+use std::{
+    collections::HashMap,
+    fmt, fs, io,
+    ops::Range,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Mutex, PoisonError},
+};
+
+use playground_metrics::{
+    find_reference, ConvertOptions, Locale, Metric, OutputStyle, ReferenceObject, DAVINCI,
+};
+use serde::{Deserialize, Serialize};
+use teloxide::types::{ChatId, MessageEntityKind};
+
+/// Whether the bot converts every message or only answers commands.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mode {
+    /// Reply to every message containing an amount.
+    #[default]
+    Auto,
+    /// Only convert when asked to with a command.
+    OnDemand,
+}
+
+/// Kinds of formatted text in a message that can be left unconverted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EntityKind {
+    Code,
+    Pre,
+    Url,
+    TextLink,
+    Mention,
+    Hashtag,
+    Cashtag,
+    Command,
+    Email,
+    Phone,
+}
+
+/// How the bot behaves in one chat.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ChatSettings {
+    /// Name of the reference object amounts are expressed in.
+    pub reference: String,
+    pub style: OutputStyle,
+    /// Metrics that are not converted. The chat's choices are saved as changes
+    /// to the defaults, so that metrics and kinds of text added later start
+    /// out the default way.
+    pub disabled: Vec<Metric>,
+    pub mode: Mode,
+    /// Metrics favoured for ambiguous aliases like "pound", most preferred
+    /// first.
+    pub prefer: Vec<Metric>,
+    /// Kinds of text never converted on top of [`EntityKind::DEFAULT_SKIP`].
+    pub skipped: Vec<EntityKind>,
+    /// Kinds of text in [`EntityKind::DEFAULT_SKIP`] converted anyway.
+    pub unskipped: Vec<EntityKind>,
+    /// How numbers are written in this chat.
+    pub locale: Locale,
+    /// The enabled metrics and skipped kinds of text as whole lists, the way
+    /// they were saved before only changes to the defaults were.
+    #[serde(rename = "metrics", skip_serializing)]
+    legacy_metrics: Option<Vec<Metric>>,
+    #[serde(rename = "skip", skip_serializing)]
+    legacy_skip: Option<Vec<EntityKind>>,
+}
+
+/// The metrics there were when settings were first saved, which are the only
+/// ones a saved list of enabled metrics can leave out on purpose.
+const LEGACY_METRICS: [Metric; 7] = [
+    Metric::Weight,
+    Metric::Length,
+    Metric::Area,
+    Metric::Volume,
+    Metric::Charge,
+    Metric::Energy,
+    Metric::Currency,
+];
+
+/// Settings of every chat that changed them, written back to a JSON file on
+/// every change so they survive restarts.
+pub struct SettingsStore {
+    path: PathBuf,
+    chats: Mutex<HashMap<i64, ChatSettings>>,
+    /// Held while an update is saved, so that concurrent updates are written
+    /// one after the other and none of them is lost.
+    saving: tokio::sync::Mutex<()>,
+}
+
+impl ChatSettings {
+    /// The chosen reference object, falling back to the davinci if it no longer
+    /// exists.
+    pub fn reference(&self) -> &'static ReferenceObject {
+        find_reference(&self.reference).unwrap_or(&DAVINCI)
+    }
+
+    /// Whether amounts of `metric` are converted in this chat.
+    pub fn converts(&self, metric: Metric) -> bool {
+        !self.disabled.contains(&metric)
+    }
+
+    /// Whether text of `kind` is left unconverted in this chat.
+    pub fn skips(&self, kind: EntityKind) -> bool {
+        if EntityKind::DEFAULT_SKIP.contains(&kind) {
+            !self.unskipped.contains(&kind)
+        } else {
+            self.skipped.contains(&kind)
+        }
+    }
+
+    pub fn set_converted(&mut self, metric: Metric, converted: bool) {
+        self.disabled.retain(|disabled| *disabled != metric);
+        if !converted {
+            self.disabled.push(metric);
+        }
+    }
+
+    pub fn set_skipped(&mut self, kind: EntityKind, skipped: bool) {
+        self.skipped.retain(|other| *other != kind);
+        self.unskipped.retain(|other| *other != kind);
+        match (EntityKind::DEFAULT_SKIP.contains(&kind), skipped) {
+            (false, true) => self.skipped.push(kind),
+            (true, false) => self.unskipped.push(kind),
+            _ => {}
+        }
+    }
+
+    /// Turns whole lists saved by an older version into changes to the
+    /// defaults.
+    fn migrate(&mut self) {
+        if let Some(metrics) = self.legacy_metrics.take() {
+            self.disabled = LEGACY_METRICS
+                .into_iter()
+                .filter(|metric| !metrics.contains(metric))
+                .collect();
+        }
+        if let Some(skip) = self.legacy_skip.take() {
+            for kind in EntityKind::ALL {
+                self.set_skipped(kind, skip.contains(&kind));
+            }
+        }
+    }
+
+    /// The conversion options of this chat, skipping the `skip` byte ranges of
+    /// the text.
+    pub fn options(&self, skip: Vec<Range<usize>>) -> ConvertOptions {
+        ConvertOptions {
+            prefer: self.prefer.clone(),
+            skip,
+            locale: self.locale,
+        }
+    }
+}
+
+impl Default for ChatSettings {
+    fn default() -> Self {
+        Self {
+            reference: DAVINCI.name.to_owned(),
+            style: OutputStyle::default(),
+            disabled: Vec::new(),
+            mode: Mode::default(),
+            prefer: Vec::new(),
+            skipped: Vec::new(),
+            unskipped: Vec::new(),
+            locale: Locale::default(),
+            legacy_metrics: None,
+            legacy_skip: None,
+        }
+    }
+}
+
+impl fmt::Display for ChatSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let metrics: Vec<&str> = Metric::ALL
+            .into_iter()
+            .filter(|&metric| self.converts(metric))
+            .map(Metric::name)
+            .collect();
+        let prefer: Vec<&str> = self.prefer.iter().map(|metric| metric.name()).collect();
+        let skip: Vec<&str> = EntityKind::ALL
+            .into_iter()
+            .filter(|&kind| self.skips(kind))
+            .map(EntityKind::name)
+            .collect();
+
+        writeln!(f, "reference: {}", self.reference().name)?;
+        writeln!(f, "style: {}", self.style)?;
+        writeln!(f, "metrics: {}", metrics.join(", "))?;
+        writeln!(f, "mode: {}", self.mode)?;
+        if prefer.is_empty() {
+            writeln!(f, "prefer: none")?;
+        } else {
+            writeln!(f, "prefer: {}", prefer.join(", "))?;
+        }
+        if skip.is_empty() {
+            writeln!(f, "skip: nothing")?;
+        } else {
+            writeln!(f, "skip: {}", skip.join(", "))?;
+        }
+        write!(f, "locale: {}", self.locale)
+    }
+}
+
+impl Mode {
+    pub const ALL: [Mode; 2] = [Mode::Auto, Mode::OnDemand];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Auto => "auto",
+            Mode::OnDemand => "on-demand",
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Mode::ALL
+            .into_iter()
+            .find(|mode| mode.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown mode `{s}`"))
+    }
+}
+
+impl EntityKind {
+    pub const ALL: [EntityKind; 10] = [
+        EntityKind::Code,
+        EntityKind::Pre,
+        EntityKind::Url,
+        EntityKind::TextLink,
+        EntityKind::Mention,
+        EntityKind::Hashtag,
+        EntityKind::Cashtag,
+        EntityKind::Command,
+        EntityKind::Email,
+        EntityKind::Phone,
+    ];
+
+    /// Everything but links with custom text, whose text reads like the rest
+    /// of the message.
+    pub const DEFAULT_SKIP: [EntityKind; 9] = [
+        EntityKind::Code,
+        EntityKind::Pre,
+        EntityKind::Url,
+        EntityKind::Mention,
+        EntityKind::Hashtag,
+        EntityKind::Cashtag,
+        EntityKind::Command,
+        EntityKind::Email,
+        EntityKind::Phone,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EntityKind::Code => "code",
+            EntityKind::Pre => "pre",
+            EntityKind::Url => "url",
+            EntityKind::TextLink => "text-link",
+            EntityKind::Mention => "mention",
+            EntityKind::Hashtag => "hashtag",
+            EntityKind::Cashtag => "cashtag",
+            EntityKind::Command => "command",
+            EntityKind::Email => "email",
+            EntityKind::Phone => "phone",
+        }
+    }
+
+    /// The kind of a Telegram entity, if it is one that can be skipped.
+    pub fn of(kind: &MessageEntityKind) -> Option<Self> {
+        match kind {
+            MessageEntityKind::Code => Some(EntityKind::Code),
+            MessageEntityKind::Pre { .. } => Some(EntityKind::Pre),
+            MessageEntityKind::Url => Some(EntityKind::Url),
+            MessageEntityKind::TextLink { .. } => Some(EntityKind::TextLink),
+            MessageEntityKind::Mention | MessageEntityKind::TextMention { .. } => {
+                Some(EntityKind::Mention)
+            }
+            MessageEntityKind::Hashtag => Some(EntityKind::Hashtag),
+            MessageEntityKind::Cashtag => Some(EntityKind::Cashtag),
+            MessageEntityKind::BotCommand => Some(EntityKind::Command),
+            MessageEntityKind::Email => Some(EntityKind::Email),
+            MessageEntityKind::PhoneNumber => Some(EntityKind::Phone),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for EntityKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for EntityKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        EntityKind::ALL
+            .into_iter()
+            .find(|kind| kind.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown kind of text `{s}`"))
+    }
+}
+
+impl SettingsStore {
+    /// Loads the settings saved at `path`, starting empty if the file does not
+    /// exist yet.
+    pub fn open(path: PathBuf) -> io::Result<Self> {
+        let mut chats: HashMap<i64, ChatSettings> = match fs::read(&path) {
+            Ok(json) => serde_json::from_slice(&json)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(err),
+        };
+
+        for settings in chats.values_mut() {
+            settings.migrate();
+        }
+
+        Ok(Self {
+            path,
+            chats: Mutex::new(chats),
+            saving: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    pub fn get(&self, chat: ChatId) -> ChatSettings {
+        self.lock().get(&chat.0).cloned().unwrap_or_default()
+    }
+
+    /// Changes the settings of `chat` and saves all settings to disk, returning
+    /// the updated settings. The change only takes effect once it is saved.
+    pub async fn update(
+        &self,
+        chat: ChatId,
+        change: impl FnOnce(&mut ChatSettings),
+    ) -> io::Result<ChatSettings> {
+        let _saving = self.saving.lock().await;
+
+        let mut chats = self.lock().clone();
+        let settings = chats.entry(chat.0).or_default();
+        change(settings);
+        let settings = settings.clone();
+
+        // Write to a temporary file first so a crash never leaves half a file
+        let json = serde_json::to_vec_pretty(&chats)?;
+        let path = self.path.clone();
+        tokio::task::spawn_blocking(move || {
+            let tmp = path.with_extension("tmp");
+            fs::write(&tmp, json)?;
+            fs::rename(&tmp, &path)
+        })
+        .await
+        .map_err(io::Error::other)??;
+
+        self.lock().insert(chat.0, settings.clone());
+        Ok(settings)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<i64, ChatSettings>> {
+        self.chats.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
@@ -0,0 +1,83 @@
+use playground_metrics::{Catalog, CatalogError};
+
+const INVALID: &str = r#"
+[[unit]]
+name = "kilogram"
+plural = "kilograms"
+metric = "weight"
+position = "after"
+factor = 1.0
+aliases = ["kg"]
+
+[[unit]]
+name = "kilo"
+plural = "kilos"
+metric = "weight"
+position = "after"
+factor = 1.0
+aliases = ["KG"]
+
+[[unit]]
+name = "metre"
+plural = "metres"
+metric = "length"
+position = "after"
+factor = 0.0
+aliases = ["m"]
+
+[[unit]]
+name = "foot"
+plural = "feet"
+metric = "length"
+position = "after"
+factor = 3.28
+aliases = []
+"#;
+
+#[test]
+fn reports_the_line_of_every_invalid_entry() {
+    let Err(CatalogError::Invalid(entries)) = Catalog::parse(INVALID) else {
+        panic!("the catalog should not be valid");
+    };
+
+    let reported: Vec<(usize, &str)> = entries
+        .iter()
+        .map(|entry| (entry.line, entry.message.as_str()))
+        .collect();
+    assert_eq!(
+        reported,
+        [
+            (10, "alias \"KG\" is already used by the entry on line 2"),
+            (18, "`factor` must be a positive number, got 0"),
+            (26, "`aliases` must not be empty"),
+        ]
+    );
+}
+
+#[test]
+fn units_of_other_metrics_may_share_an_alias() {
+    let source = r#"
+[[unit]]
+name = "pound"
+plural = "pounds"
+metric = "weight"
+position = "after"
+factor = 2.2
+aliases = ["pound"]
+
+[[unit]]
+name = "pound sterling"
+plural = "pounds sterling"
+metric = "currency"
+position = "after"
+factor = 0.11
+aliases = ["pound"]
+"#;
+
+    assert_eq!(Catalog::parse(source).unwrap().units().len(), 2);
+}
+
+#[test]
+fn the_builtin_catalog_is_valid() {
+    assert!(!Catalog::default().units().is_empty());
+}
@@ -0,0 +1,355 @@
+use playground_metrics::{
+    Conversion, ConversionError, ConvertOptions, Converter, Locale, Metric, OutputStyle, Precision,
+    ReverseError, Rewritten, DAVINCI,
+};
+
+/// Converts `text` and checks the conversions are sorted and never overlap,
+/// which rewriting relies on.
+fn convert<'a>(converter: &'a Converter, text: &str) -> Vec<Conversion<'a>> {
+    convert_in(converter, text, Locale::Auto)
+}
+
+/// Like [`convert`], reading numbers the way `locale` says.
+fn convert_in<'a>(converter: &'a Converter, text: &str, locale: Locale) -> Vec<Conversion<'a>> {
+    let options = ConvertOptions {
+        locale,
+        ..ConvertOptions::default()
+    };
+    let conversions: Vec<Conversion> = converter
+        .try_convert_text(text, &DAVINCI, &options)
+        .into_iter()
+        .filter_map(Result::ok)
+        .collect();
+
+    for pair in conversions.windows(2) {
+        assert!(
+            pair[0].span.end <= pair[1].span.start,
+            "{text:?}: {:?} overlaps {:?}",
+            pair[0].span,
+            pair[1].span
+        );
+    }
+    for style in OutputStyle::ALL {
+        let _ = Rewritten::new(text, &conversions, style);
+    }
+
+    conversions
+}
+
+#[test]
+fn range_never_takes_the_amount_of_a_price() {
+    let converter = Converter::default();
+
+    for text in ["$5-10 kg", "€5 - 10 kg", "$5 to 10 kg", "$5±2 kg"] {
+        let conversions = convert(&converter, text);
+        assert_eq!(conversions.len(), 2, "{text:?}");
+        assert_eq!(conversions[0].metric(), Metric::Currency, "{text:?}");
+        assert_eq!(conversions[0].amount, 5.0, "{text:?}");
+    }
+}
+
+#[test]
+fn ranges_and_tolerances_keep_both_figures() {
+    let converter = Converter::default();
+
+    let range = convert(&converter, "5-10 kg");
+    assert_eq!(range.len(), 1);
+    assert_eq!(range[0].span, 0..7);
+    assert_eq!(range[0].amount, 5.0);
+
+    let tolerance = convert(&converter, "5±0.2 mm");
+    assert_eq!(tolerance.len(), 1);
+    assert_eq!(tolerance[0].amount, 5.0);
+}
+
+#[test]
+fn dimensions_never_take_the_amount_of_a_price() {
+    let converter = Converter::default();
+
+    for text in ["$5 x 3 m", "$5x3 m", "€5 by 3 m"] {
+        let conversions = convert(&converter, text);
+        assert_eq!(conversions.len(), 2, "{text:?}");
+        assert_eq!(conversions[1].metric(), Metric::Length, "{text:?}");
+    }
+
+    for text in ["40 x 30 cm", "2m x 3m", "2 m by 3 m", "4 by 100 m"] {
+        let area = convert(&converter, text);
+        assert_eq!(area.len(), 1, "{text:?}");
+        assert_eq!(area[0].metric(), Metric::Area, "{text:?}");
+    }
+}
+
+#[test]
+fn a_count_times_a_length_stays_a_length() {
+    let converter = Converter::default();
+
+    let relay = convert(&converter, "a 4 x 100 m relay");
+    assert_eq!(relay.len(), 1);
+    assert_eq!(relay[0].metric(), Metric::Length);
+    assert_eq!(relay[0].amount, 100.0);
+    assert_eq!(relay[0].span, 6..11);
+}
+
+#[test]
+fn case_tells_milliwatts_from_megawatts() {
+    let converter = Converter::default();
+
+    for (text, unit) in [
+        ("a 500 mW laser", "milliwatt"),
+        ("a 5 MW plant", "megawatt"),
+        ("3 mWh", "milliwatt hour"),
+        ("2 MWh", "megawatt hour"),
+        ("10 MWh per hour", "megawatt hour"),
+    ] {
+        let conversions = convert(&converter, text);
+        assert_eq!(conversions.len(), 1, "{text:?}");
+        assert_eq!(conversions[0].unit.name, unit, "{text:?}");
+    }
+}
+
+#[test]
+fn case_tells_bits_from_bytes() {
+    let converter = Converter::default();
+
+    for (text, unit) in [
+        ("100 Mb/s", "megabit"),
+        ("a 5 Mb file", "megabit"),
+        ("10 Kb", "kilobit"),
+        ("10 kb", "kilobit"),
+        ("10 kB", "kilobyte"),
+        ("100 MB/s", "megabyte"),
+        ("a 5 MB file", "megabyte"),
+        ("16gb of ram", "gigabyte"),
+    ] {
+        let conversions = convert(&converter, text);
+        assert_eq!(conversions.len(), 1, "{text:?}");
+        assert_eq!(conversions[0].unit.name, unit, "{text:?}");
+    }
+}
+
+#[test]
+fn millions_and_billions_only_multiply_money() {
+    let converter = Converter::default();
+
+    for (text, amount) in [
+        ("raised $5m", 5e6),
+        ("a $2bn deal", 2e9),
+        ("5m € budget", 5e6),
+    ] {
+        let conversions = convert(&converter, text);
+        assert_eq!(conversions.len(), 1, "{text:?}");
+        assert_eq!(conversions[0].metric(), Metric::Currency, "{text:?}");
+        assert_eq!(conversions[0].amount, amount, "{text:?}");
+    }
+
+    assert!(convert(&converter, "5m users").is_empty());
+    assert!(convert(&converter, "5m people").is_empty());
+    assert!(convert(&converter, "5t of it").is_empty());
+}
+
+#[test]
+fn metres_written_straight_after_the_digits() {
+    let converter = Converter::default();
+
+    for (text, metric) in [
+        ("ran 5m", Metric::Length),
+        ("a 10m pole", Metric::Length),
+        ("a 2m x 3m room", Metric::Area),
+        ("2m by 3m", Metric::Area),
+    ] {
+        let conversions = convert(&converter, text);
+        assert_eq!(conversions.len(), 1, "{text:?}");
+        assert_eq!(conversions[0].metric(), metric, "{text:?}");
+    }
+}
+
+#[test]
+fn small_ranges_and_tolerances_keep_their_figures() {
+    let converter = Converter::default();
+
+    let tolerance = convert(&converter, "5±0.2 mm")[0].to_string();
+    assert_eq!(tolerance, "0.03191±0.001276 davincis");
+
+    let range = convert(&converter, "1-2 mm")[0].to_string();
+    assert_eq!(range, "0.006382–0.01276 davincis");
+
+    assert_eq!(
+        convert(&converter, "0.1 mm")[0].to_string(),
+        "0.0006382 davincis"
+    );
+}
+
+#[test]
+fn reverse_queries_answer_in_rates() {
+    let converter = Converter::default();
+    let options = ConvertOptions::default();
+
+    let answers = converter.convert_reverse("1 davinci to km/h", &options);
+    let answer = answers[0].as_ref().expect("km/h is a known unit");
+    assert_eq!(answer.span, 0..17);
+    assert_eq!(answer.per.map(|per| per.name.as_str()), Some("hour"));
+    assert_eq!(
+        answer.to_string(),
+        "1 davinci is 0.5641 kilometres per hour"
+    );
+
+    let answers = converter.convert_reverse("1 davinci in km per fortnight", &options);
+    assert!(
+        matches!(&answers[0], Err(ReverseError::UnknownUnit(unit)) if unit == "km per fortnight")
+    );
+}
+
+#[test]
+fn capital_k_is_kelvin_unless_it_multiplies() {
+    let converter = Converter::default();
+
+    for text in ["300 K", "300K", "cooled to 5K."] {
+        let conversions = convert(&converter, text);
+        assert_eq!(conversions.len(), 1, "{text:?}");
+        assert_eq!(conversions[0].unit.name, "kelvin", "{text:?}");
+    }
+
+    for text in ["20K €", "$20K"] {
+        let conversions = convert(&converter, text);
+        assert_eq!(conversions[0].metric(), Metric::Currency, "{text:?}");
+        assert_eq!(conversions[0].amount, 20_000.0, "{text:?}");
+    }
+
+    assert!(convert(&converter, "300K followers").is_empty());
+    assert!(convert(&converter, "300k").is_empty());
+}
+
+#[test]
+fn below_zero_is_a_reading_and_below_freezing_a_change() {
+    let converter = Converter::default();
+
+    let below_zero = convert(&converter, "it was 10 °C below zero.");
+    assert_eq!(below_zero.len(), 1);
+    assert_eq!(below_zero[0].amount, -10.0);
+    assert!(!below_zero[0].difference);
+    assert_eq!(below_zero[0].span, 7..24);
+
+    let above_zero = convert(&converter, "5 °C above zero");
+    assert_eq!(above_zero[0].amount, 5.0);
+    assert!(!above_zero[0].difference);
+
+    assert!(convert(&converter, "10 °C below freezing")[0].difference);
+}
+
+#[test]
+fn between_keeps_the_sign_of_a_temperature() {
+    let converter = Converter::default();
+
+    let range = convert(&converter, "between -5 and 10 °C");
+    assert_eq!(range.len(), 1);
+    assert_eq!(range[0].span.start, 0);
+    assert_eq!(range[0].amount, -5.0);
+    assert!(matches!(range[0].precision, Precision::Range { amount, .. } if amount == 10.0));
+}
+
+#[test]
+fn separators_and_decimal_marks() {
+    let converter = Converter::default();
+
+    for (text, amount) in [
+        ("1,500 kg", 1500.0),
+        ("1,5 kg", 1.5),
+        ("1.500,00 €", 1500.0),
+        ("1'500 kg", 1500.0),
+        ("1,00,000 ₹", 100_000.0),
+        ("1,299.99 kg", 1299.99),
+    ] {
+        let conversions = convert(&converter, text);
+        assert_eq!(conversions.len(), 1, "{text:?}");
+        assert_eq!(conversions[0].amount, amount, "{text:?}");
+        assert_eq!(conversions[0].span, 0..text.len(), "{text:?}");
+    }
+
+    // A list of numbers is not one number, nor does its last part stand alone
+    assert!(convert(&converter, "1,2,3 kg").is_empty());
+}
+
+#[test]
+fn locales_settle_what_a_comma_means() {
+    let converter = Converter::default();
+
+    assert_eq!(
+        convert_in(&converter, "1,500 kg", Locale::Comma)[0].amount,
+        1.5
+    );
+    assert_eq!(
+        convert_in(&converter, "1,500 kg", Locale::Point)[0].amount,
+        1500.0
+    );
+    assert!(convert_in(&converter, "1,5 kg", Locale::Point).is_empty());
+
+    // With a decimal comma, "1,299.99" is no number at all
+    assert!(convert_in(&converter, "1,299.99 kg", Locale::Comma).is_empty());
+    assert!(convert_in(&converter, "$1,299.99", Locale::Comma).is_empty());
+}
+
+#[test]
+fn fractions_and_mixed_numbers() {
+    let converter = Converter::default();
+
+    for (text, amount) in [
+        ("1/2 kg", 0.5),
+        ("2 1/4 inches", 2.25),
+        ("½ litre", 0.5),
+        ("1½ kg", 1.5),
+    ] {
+        let conversions = convert(&converter, text);
+        assert_eq!(conversions.len(), 1, "{text:?}");
+        assert_eq!(conversions[0].amount, amount, "{text:?}");
+        assert_eq!(conversions[0].span, 0..text.len(), "{text:?}");
+    }
+
+    // Dates are not fractions, nor are their last parts
+    assert!(convert(&converter, "1/2/2024").is_empty());
+    assert!(convert(&converter, "on 1/2/2024 kg").is_empty());
+
+    // An improper fraction is no part of a mixed number, but stands alone
+    let improper = convert(&converter, "2 10/5 kg");
+    assert_eq!(improper.len(), 1);
+    assert_eq!(improper[0].amount, 2.0);
+    assert_eq!(improper[0].span, 2..9);
+}
+
+#[test]
+fn zero_denominators_are_out_of_range() {
+    let converter = Converter::default();
+
+    for text in ["1/0 kg", "2 1/0 kg"] {
+        let results = converter.try_convert_text(text, &DAVINCI, &ConvertOptions::default());
+        assert_eq!(results.len(), 1, "{text:?}");
+        assert!(
+            matches!(results[0], Err(ConversionError::OutOfRange(_))),
+            "{text:?}: {:?}",
+            results[0]
+        );
+    }
+}
+
+#[test]
+fn mixed_quantities_become_one_conversion() {
+    let converter = Converter::default();
+
+    for (text, amount, unit) in [
+        ("5 ft 11 in", 71.0, "inch"),
+        ("1 h 20 min", 80.0, "minute"),
+        ("1 kg and 500 g", 1500.0, "gram"),
+        ("1 kg 200 g", 1200.0, "gram"),
+    ] {
+        let conversions = convert(&converter, text);
+        assert_eq!(conversions.len(), 1, "{text:?}");
+        assert_eq!(conversions[0].amount, amount, "{text:?}");
+        assert_eq!(conversions[0].unit.name, unit, "{text:?}");
+        assert_eq!(conversions[0].span, 0..text.len(), "{text:?}");
+    }
+
+    // Money in two currencies is two amounts, not one
+    let prices = convert(&converter, "$5 €3");
+    assert_eq!(prices.len(), 2);
+    assert_eq!(prices[0].unit.name, "US dollar");
+    assert_eq!(prices[1].unit.name, "euro");
+}